@@ -2,18 +2,30 @@ use cosmwasm_std::{
     entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
 };
 
+use cw2::{set_contract_version, CONTRACT};
 use cw_otc_common::{
     definitions::Config,
     msgs::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg},
 };
 
 use crate::{
-    execute::{run_cancel_otc, run_claim_otc, run_create_otc, run_execute_otc},
-    query::{qy_position, qy_positions},
+    execute::{
+        run_accept_counter_offer, run_accept_ownership, run_accept_position_side, run_cancel_otc,
+        run_claim_many, run_claim_otc, run_counter_offer, run_create_otc, run_deposit_otc,
+        run_execute_otc, run_propose_new_owner, run_receive, run_receive_nft,
+        run_reject_counter_offer, run_set_guardian, run_set_pause, run_set_payout_address,
+        run_transfer_position_side, run_update_config, run_update_otc,
+    },
+    functions::Deposit,
+    migrations::{assert_migration, migrate_state},
+    query::{qy_claimable, qy_config, qy_counter_offers, qy_position, qy_positions},
     response::ContractResponse,
     state::CONFIG,
 };
 
+pub const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
@@ -26,8 +38,14 @@ pub fn instantiate(
         deps.api.addr_validate(&msg.owner)?,
         msg.fee,
         deps.api.addr_validate(&msg.fee_collector)?,
+        msg.strict_funds.unwrap_or(false),
+        msg.trade_fee.unwrap_or_default(),
+        msg.position_nft
+            .map(|val| deps.api.addr_validate(&val))
+            .transpose()?,
     )?;
     CONFIG.save(deps.storage, &config)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::new()
         .add_attribute("action", "instantiate")
@@ -37,16 +55,43 @@ pub fn instantiate(
 #[entry_point]
 pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> ContractResponse {
     match msg {
-        ExecuteMsg::CreateOtc(msg) => run_create_otc(deps, env, info, msg),
-        ExecuteMsg::ExecuteOtc(msg) => run_execute_otc(deps, env, info, msg),
+        ExecuteMsg::CreateOtc(msg) => {
+            run_create_otc(deps, env, info.sender, Deposit::from_funds(info.funds), msg)
+        }
+        ExecuteMsg::ExecuteOtc(msg) => {
+            run_execute_otc(deps, env, info.sender, Deposit::from_funds(info.funds), msg)
+        }
+        ExecuteMsg::Receive(msg) => run_receive(deps, env, info, msg),
+        ExecuteMsg::ReceiveNft(msg) => run_receive_nft(deps, env, info, msg),
+        ExecuteMsg::DepositOtc(msg) => {
+            run_deposit_otc(deps, env, info.sender, Deposit::from_funds(info.funds), msg)
+        }
+        ExecuteMsg::UpdateOtc(msg) => {
+            run_update_otc(deps, env, info.sender, Deposit::from_funds(info.funds), msg)
+        }
+        ExecuteMsg::CounterOffer(msg) => {
+            run_counter_offer(deps, env, info.sender, Deposit::from_funds(info.funds), msg)
+        }
+        ExecuteMsg::AcceptCounterOffer(msg) => run_accept_counter_offer(deps, env, info, msg),
+        ExecuteMsg::RejectCounterOffer(msg) => run_reject_counter_offer(deps, env, info, msg),
         ExecuteMsg::ClaimOtc(msg) => run_claim_otc(deps, env, info, msg),
+        ExecuteMsg::ClaimMany(msg) => run_claim_many(deps, env, info, msg),
+        ExecuteMsg::SetPayoutAddress(msg) => run_set_payout_address(deps, info, msg),
+        ExecuteMsg::TransferPositionSide(msg) => run_transfer_position_side(deps, info, msg),
+        ExecuteMsg::AcceptPositionSide(msg) => run_accept_position_side(deps, info, msg),
         ExecuteMsg::CancelOtc(msg) => run_cancel_otc(deps, env, info, msg),
+        ExecuteMsg::UpdateConfig(msg) => run_update_config(deps, info, msg),
+        ExecuteMsg::SetPause(msg) => run_set_pause(deps, info, msg),
+        ExecuteMsg::SetGuardian(msg) => run_set_guardian(deps, info, msg),
+        ExecuteMsg::ProposeNewOwner(msg) => run_propose_new_owner(deps, info, msg),
+        ExecuteMsg::AcceptOwnership {} => run_accept_ownership(deps, info),
     }
 }
 
 #[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
+        QueryMsg::Config {} => to_json_binary(&qy_config(deps)?),
         QueryMsg::Position { id } => to_json_binary(&qy_position(deps, id)?),
         QueryMsg::Positions {
             limit,
@@ -54,10 +99,33 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             filters,
             order,
         } => to_json_binary(&qy_positions(deps, start_after, limit, filters, order)?),
+        QueryMsg::Claimable {
+            id,
+            address,
+            at_time,
+            at_height,
+        } => to_json_binary(&qy_claimable(deps, env, id, address, at_time, at_height)?),
+        QueryMsg::CounterOffers {
+            id,
+            limit,
+            start_after,
+        } => to_json_binary(&qy_counter_offers(deps, id, start_after, limit)?),
     }
 }
 
 #[entry_point]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> ContractResponse {
-    Ok(Response::default())
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> ContractResponse {
+    let from_version = assert_migration(
+        CONTRACT.may_load(deps.storage)?,
+        CONTRACT_NAME,
+        CONTRACT_VERSION,
+    )?;
+
+    migrate_state(deps.storage, &from_version)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", from_version)
+        .add_attribute("to_version", CONTRACT_VERSION))
 }
@@ -1,37 +1,88 @@
-use cosmwasm_std::{Addr, DepsMut, Env, MessageInfo, Response, StdError};
+use std::cmp::min;
+
+use cosmwasm_std::{from_json, Addr, Decimal, DepsMut, Env, MessageInfo, Order, Response};
+use cw20::Cw20ReceiveMsg;
+use cw721::Cw721ReceiveMsg;
 use cw_otc_common::{
-    definitions::OtcPosition,
-    msgs::{CancelOtcMsg, ClaimOtcMsg, CreateOtcMsg, ExecuteOtcMsg},
+    definitions::{
+        Config, CounterOffer, OtcItem, OtcItemInfo, OtcPosition, OtcPositionStatus, PauseAction,
+        PositionSide,
+    },
+    error::ContractResult,
+    msgs::{
+        AcceptPositionSideMsg, CancelOtcMsg, ClaimManyMsg, ClaimOtcMsg, CounterOfferActionMsg,
+        CounterOfferMsg, CreateOtcMsg, Cw20HookMsg, Cw721HookMsg, DepositOtcMsg, ExecuteOtcMsg,
+        ProposeNewOwnerMsg, QueryPositionsFilterStatus, SetGuardianMsg, SetPauseMsg,
+        SetPayoutAddressMsg, TransferPositionSideMsg, UpdateConfigMsg, UpdateOtcMsg,
+    },
 };
 
 use crate::{
-    functions::{after_action, cancel_otc, collect_otc_items, send_fee, send_otc_items},
+    functions::{
+        after_action, build_send_otc_info_items, cancel_otc, claim_otc_items, claimer_sides,
+        collect_otc_items, deposit_otc_items, deposited_items, diff_otc_items,
+        get_executors_index_values, get_multi_index_values, merge_otc_items_info,
+        mint_position_nfts, refund_deposit, release_otc_items, send_fee, send_otc_items, Deposit,
+        DEFAULT_LIMIT, MAX_LIMIT,
+    },
     response::{ContractError, ContractResponse},
-    state::{positions, CONFIG},
+    state::{positions, CONFIG, COUNTER_OFFERS},
 };
 
 pub fn run_create_otc(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo,
+    sender: Addr,
+    deposit: Deposit,
     msg: CreateOtcMsg,
 ) -> ContractResponse {
     let mut config = CONFIG.load(deps.storage)?;
+    config.assert_not_paused(PauseAction::Create)?;
     config.counter_otc += 1;
 
-    let position = OtcPosition::from_create_otc_msg(
+    let mut position = OtcPosition::from_create_otc_msg(
         deps.as_ref(),
         &env,
         msg,
         config.counter_otc,
-        info.sender.clone(),
+        sender.clone(),
+        config.trade_fee.clone(),
     )?;
     position.validate(deps.as_ref())?;
 
-    let (msgs_deposit, remaining_coins) =
-        collect_otc_items(&env, &position.offer, info.sender, info.funds)?;
+    if config.strict_funds {
+        deposit.assert_expected(
+            position
+                .offer
+                .iter()
+                .map(|val| &val.item_info)
+                .chain(config.fee.iter()),
+        )?;
+    }
+
+    let (msgs_fee, deposit) = send_fee(
+        deps.as_ref(),
+        &env,
+        &config.fee,
+        &config.fee_collector,
+        &sender,
+        deposit,
+    )?;
+
+    let (msgs_deposit, deposit) = if position.status.is_in_funding() {
+        deposit_otc_items(
+            deps.as_ref(),
+            &env,
+            &mut position.offer,
+            None,
+            &sender,
+            deposit,
+        )?
+    } else {
+        collect_otc_items(deps.as_ref(), &env, &mut position.offer, &sender, deposit)?
+    };
 
-    let msgs_fee = send_fee(&env, &config.fee, &config.fee_collector, remaining_coins)?;
+    position.try_fund();
 
     CONFIG.save(deps.storage, &config)?;
 
@@ -40,50 +91,613 @@ pub fn run_create_otc(
     Ok(Response::new()
         .add_messages(msgs_deposit)
         .add_messages(msgs_fee)
+        .add_messages(refund_deposit(&sender, deposit)?)
         .add_attribute("action", "create_orc")
         .add_attribute(
             "executor",
             position.executor.unwrap_or(Addr::unchecked("undefined")),
         )
-        .add_attribute("otc_id", config.counter_otc.to_string()))
+        .add_attribute("otc_id", config.counter_otc.to_string())
+        .add_attribute("status", position.status.as_string_ref())
+        .add_attribute(
+            "expires_at",
+            position
+                .expires_at
+                .map(|val| val.to_string())
+                .unwrap_or("never".to_string()),
+        ))
 }
 
 pub fn run_execute_otc(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
-    info: MessageInfo,
+    sender: Addr,
+    deposit: Deposit,
     msg: ExecuteOtcMsg,
 ) -> ContractResponse {
     let mut position = positions().load(deps.storage, msg.id)?;
-    position.active(&env, &info.sender)?;
-
     let config = CONFIG.load(deps.storage)?;
+    config.assert_not_paused(PauseAction::Execute)?;
+
+    if position.partial_fill.is_some() {
+        return run_fill_otc(deps, env, sender, deposit, position, config, msg.fill);
+    }
+
+    if msg.fill.is_some() {
+        return Err(ContractError::FillNotAllowed {});
+    }
+
+    // The fee has already been paid with the first staged deposit
+    let fee = if position.fee_paid {
+        vec![]
+    } else {
+        config.fee.clone()
+    };
+
+    position.active(&env, &sender)?;
+
+    if config.strict_funds {
+        deposit.assert_expected(
+            position
+                .ask
+                .iter()
+                .map(|val| &val.item_info)
+                .chain(fee.iter()),
+        )?;
+    }
+
+    let (msgs_fee, deposit) = send_fee(
+        deps.as_ref(),
+        &env,
+        &fee,
+        &config.fee_collector,
+        &sender,
+        deposit,
+    )?;
+
+    let (msgs_deposit, deposit) =
+        collect_otc_items(deps.as_ref(), &env, &mut position.ask, &sender, deposit)?;
+
+    let msgs_release = release_otc_items(&env, &mut position, &config.fee_collector)?;
+
+    let attrs_close = after_action(deps.branch(), &env, &mut position)?;
 
-    let (msgs_deposit, remaining_coins) =
-        collect_otc_items(&env, &position.ask, info.sender, info.funds)?;
+    let msgs_nft = mint_position_nfts(deps, &mut position, &config)?;
 
-    let msgs_fee = send_fee(&env, &config.fee, &config.fee_collector, remaining_coins)?;
+    Ok(Response::new()
+        .add_messages(msgs_deposit)
+        .add_messages(msgs_fee)
+        .add_messages(refund_deposit(&sender, deposit)?)
+        .add_messages(msgs_release)
+        .add_messages(msgs_nft)
+        .add_attribute("action", "execute_otc")
+        .add_attribute("otc_id", msg.id.to_string())
+        .add_attributes(attrs_close))
+}
 
-    let msgs_to_owner = send_otc_items(&env, &mut position.ask, &position.status, &position.owner)?;
+/// Execute a fraction of a partial fill position, the position stays open until completely filled
+fn run_fill_otc(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    deposit: Deposit,
+    mut position: OtcPosition,
+    config: Config,
+    fill: Option<Decimal>,
+) -> ContractResponse {
+    if !position.status.is_in_pending() {
+        return Err(ContractError::InvalidStatus {
+            action: "fill".to_string(),
+            status: position.status.clone(),
+        });
+    }
+
+    if position.is_expired(&env.block) {
+        return Err(ContractError::PositionExpired { id: position.id });
+    }
+
+    position.assert_executor(&sender)?;
+
+    let mut partial_fill = position.partial_fill.clone().unwrap();
+    let filled = partial_fill.assert_fill(fill.unwrap_or(Decimal::one() - partial_fill.filled))?;
+
+    let mut ask = fill_otc_items(&position.ask, &partial_fill, filled)?;
+    let mut offer = fill_otc_items(&position.offer, &partial_fill, filled)?;
+
+    if config.strict_funds {
+        deposit.assert_expected(
+            ask.iter()
+                .map(|val| &val.item_info)
+                .chain(config.fee.iter()),
+        )?;
+    }
+
+    let (msgs_fee, deposit) = send_fee(
+        deps.as_ref(),
+        &env,
+        &config.fee,
+        &config.fee_collector,
+        &sender,
+        deposit,
+    )?;
+
+    let (msgs_deposit, deposit) =
+        collect_otc_items(deps.as_ref(), &env, &mut ask, &sender, deposit)?;
+
+    let msgs_to_owner = send_otc_items(
+        &env,
+        &mut ask,
+        &position.status,
+        position.owner_receiver(),
+        position.trade_fee.maker_bps,
+        &config.fee_collector,
+    )?;
     let msgs_to_executor = send_otc_items(
         &env,
-        &mut position.offer,
+        &mut offer,
         &position.status,
-        &position.executor.clone().unwrap(),
+        &sender,
+        position.trade_fee.taker_bps,
+        &config.fee_collector,
     )?;
 
-    let attrs_close = after_action(deps, &env, &mut position)?;
+    partial_fill.filled = filled;
+    position.partial_fill = Some(partial_fill);
+
+    if filled == Decimal::one() {
+        position.status = OtcPositionStatus::Executed(env.block.time.seconds());
+    }
+
+    positions().save(deps.storage, position.id, &position)?;
 
     Ok(Response::new()
         .add_messages(msgs_deposit)
         .add_messages(msgs_fee)
+        .add_messages(refund_deposit(&sender, deposit)?)
         .add_messages(msgs_to_owner)
         .add_messages(msgs_to_executor)
-        .add_attribute("action", "execute_otc")
+        .add_attribute("action", "fill_otc")
+        .add_attribute("otc_id", position.id.to_string())
+        .add_attribute("executor", sender)
+        .add_attribute("filled", filled.to_string())
+        .add_attribute("status", position.status.as_string_ref()))
+}
+
+pub fn run_deposit_otc(
+    mut deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    deposit: Deposit,
+    msg: DepositOtcMsg,
+) -> ContractResponse {
+    let mut position = positions().load(deps.storage, msg.id)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    // Funding the offer completes the creation, depositing the ask stages the execution
+    match position.status {
+        OtcPositionStatus::Funding => config.assert_not_paused(PauseAction::Create)?,
+        OtcPositionStatus::Pending => config.assert_not_paused(PauseAction::Execute)?,
+        _ => {}
+    }
+
+    if position.is_expired(&env.block) {
+        return Err(ContractError::PositionExpired { id: position.id });
+    }
+
+    let response = Response::new()
+        .add_attribute("action", "deposit_otc")
+        .add_attribute("otc_id", msg.id.to_string());
+
+    match position.status {
+        OtcPositionStatus::Funding => {
+            if sender != position.owner {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            if config.strict_funds {
+                deposit.assert_expected(position.offer.iter().map(|val| &val.item_info))?;
+            }
+
+            let (msgs_deposit, deposit) = deposit_otc_items(
+                deps.as_ref(),
+                &env,
+                &mut position.offer,
+                msg.items.as_deref(),
+                &sender,
+                deposit,
+            )?;
+
+            position.try_fund();
+
+            positions().save(deps.storage, msg.id, &position)?;
+
+            Ok(response
+                .add_messages(msgs_deposit)
+                .add_messages(refund_deposit(&sender, deposit)?)
+                .add_attribute("status", position.status.as_string_ref()))
+        }
+        OtcPositionStatus::Pending => {
+            // Staged execution is reserved to the designated executor, otherwise anyone could lock an open position
+            if position.executor.as_ref() != Some(&sender) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            if position.partial_fill.is_some() {
+                return Err(ContractError::PartialFillNotAllowed {
+                    action: "staged_execution".to_string(),
+                });
+            }
+
+            // The fee is paid with the first deposit
+            let fee = if position.fee_paid {
+                vec![]
+            } else {
+                config.fee.clone()
+            };
+
+            if config.strict_funds {
+                deposit.assert_expected(
+                    position
+                        .ask
+                        .iter()
+                        .map(|val| &val.item_info)
+                        .chain(fee.iter()),
+                )?;
+            }
+
+            let (msgs_fee, deposit) = send_fee(
+                deps.as_ref(),
+                &env,
+                &fee,
+                &config.fee_collector,
+                &sender,
+                deposit,
+            )?;
+
+            position.fee_paid = true;
+
+            let (msgs_deposit, deposit) = deposit_otc_items(
+                deps.as_ref(),
+                &env,
+                &mut position.ask,
+                msg.items.as_deref(),
+                &sender,
+                deposit,
+            )?;
+
+            let response = response
+                .add_messages(msgs_deposit)
+                .add_messages(msgs_fee)
+                .add_messages(refund_deposit(&sender, deposit)?);
+
+            if !position.is_ask_deposited() {
+                positions().save(deps.storage, msg.id, &position)?;
+                return Ok(response.add_attribute("status", position.status.as_string_ref()));
+            }
+
+            position.active(&env, &sender)?;
+
+            let msgs_release = release_otc_items(&env, &mut position, &config.fee_collector)?;
+
+            let attrs_close = after_action(deps.branch(), &env, &mut position)?;
+
+            let msgs_nft = mint_position_nfts(deps, &mut position, &config)?;
+
+            Ok(response
+                .add_messages(msgs_release)
+                .add_messages(msgs_nft)
+                .add_attributes(attrs_close))
+        }
+        _ => Err(ContractError::InvalidStatus {
+            action: "deposit".to_string(),
+            status: position.status.clone(),
+        }),
+    }
+}
+
+/// Update a `Pending` position, reconciling the `offer` assets already escrowed with the new ones
+pub fn run_update_otc(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    deposit: Deposit,
+    msg: UpdateOtcMsg,
+) -> ContractResponse {
+    let mut position = positions().load(deps.storage, msg.id)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    // A new offer escrows funds as the creation does
+    if msg.offer.is_some() {
+        config.assert_not_paused(PauseAction::Create)?;
+    }
+
+    if sender != position.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if !position.status.is_in_pending() {
+        return Err(ContractError::InvalidStatus {
+            action: "update".to_string(),
+            status: position.status.clone(),
+        });
+    }
+
+    if position.has_ask_deposits() {
+        return Err(ContractError::AskDeposited {
+            action: "update".to_string(),
+        });
+    }
+
+    if matches!(&position.partial_fill, Some(partial_fill) if !partial_fill.filled.is_zero()) {
+        return Err(ContractError::PartialFillNotAllowed {
+            action: "update".to_string(),
+        });
+    }
+
+    if let Some(executors) = msg.executors {
+        position.set_executors(deps.as_ref(), &executors)?;
+    }
+
+    if let Some(ask) = msg.ask {
+        position.ask = ask.into_iter().map(|val| val.into()).collect();
+    }
+
+    if let Some(expires_at) = msg.expires_at {
+        if expires_at.is_expired(&env.block) {
+            return Err(ContractError::ExpirationPassed { expires_at });
+        }
+        position.expires_at = Some(expires_at);
+    }
+
+    let (msgs_deposit, msgs_refund, deposit) = if let Some(offer) = msg.offer {
+        let mut offer: Vec<OtcItem> = offer.into_iter().map(|val| val.into()).collect();
+
+        let (mut to_deposit, to_refund) = diff_otc_items(&position.offer, &offer)?;
+
+        if config.strict_funds {
+            deposit.assert_expected(to_deposit.iter().map(|val| &val.item_info))?;
+        }
+
+        let (msgs_deposit, deposit) =
+            collect_otc_items(deps.as_ref(), &env, &mut to_deposit, &sender, deposit)?;
+
+        for item in offer.iter_mut() {
+            item.deposited = true;
+        }
+
+        position.offer = offer;
+
+        (
+            msgs_deposit,
+            build_send_otc_info_items(&env, &to_refund, &position.owner)?,
+            deposit,
+        )
+    } else {
+        if config.strict_funds {
+            deposit.assert_expected(std::iter::empty())?;
+        }
+
+        (vec![], vec![], deposit)
+    };
+
+    position.validate(deps.as_ref())?;
+
+    positions().save(deps.storage, msg.id, &position)?;
+
+    Ok(Response::new()
+        .add_messages(msgs_deposit)
+        .add_messages(msgs_refund)
+        .add_messages(refund_deposit(&sender, deposit)?)
+        .add_attribute("action", "update_otc")
+        .add_attribute("otc_id", msg.id.to_string()))
+}
+
+/// Escrow an alternative `ask` for a `Pending` position, the fee is paid on submission
+pub fn run_counter_offer(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    deposit: Deposit,
+    msg: CounterOfferMsg,
+) -> ContractResponse {
+    let position = positions().load(deps.storage, msg.id)?;
+    let config = CONFIG.load(deps.storage)?;
+    config.assert_not_paused(PauseAction::Execute)?;
+
+    if !position.status.is_in_pending() {
+        return Err(ContractError::InvalidStatus {
+            action: "counter_offer".to_string(),
+            status: position.status.clone(),
+        });
+    }
+
+    if position.is_expired(&env.block) {
+        return Err(ContractError::PositionExpired { id: position.id });
+    }
+
+    if position.partial_fill.is_some() {
+        return Err(ContractError::PartialFillNotAllowed {
+            action: "counter_offer".to_string(),
+        });
+    }
+
+    if sender == position.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    position.assert_executor(&sender)?;
+
+    if COUNTER_OFFERS.has(deps.storage, (msg.id, &sender)) {
+        return Err(ContractError::CounterOfferExists {
+            proposer: sender.to_string(),
+        });
+    }
+
+    let mut counter_offer =
+        CounterOffer::from_counter_offer_msg(deps.as_ref(), &env, msg, sender.clone())?;
+
+    if config.strict_funds {
+        deposit.assert_expected(
+            counter_offer
+                .ask
+                .iter()
+                .map(|val| &val.item_info)
+                .chain(config.fee.iter()),
+        )?;
+    }
+
+    let (msgs_fee, deposit) = send_fee(
+        deps.as_ref(),
+        &env,
+        &config.fee,
+        &config.fee_collector,
+        &sender,
+        deposit,
+    )?;
+
+    let (msgs_deposit, deposit) = collect_otc_items(
+        deps.as_ref(),
+        &env,
+        &mut counter_offer.ask,
+        &sender,
+        deposit,
+    )?;
+
+    COUNTER_OFFERS.save(deps.storage, (counter_offer.id, &sender), &counter_offer)?;
+
+    Ok(Response::new()
+        .add_messages(msgs_deposit)
+        .add_messages(msgs_fee)
+        .add_messages(refund_deposit(&sender, deposit)?)
+        .add_attribute("action", "counter_offer")
+        .add_attribute("otc_id", counter_offer.id.to_string())
+        .add_attribute("proposer", sender))
+}
+
+/// Execute the position at the terms of the counter offer
+pub fn run_accept_counter_offer(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: CounterOfferActionMsg,
+) -> ContractResponse {
+    let mut position = positions().load(deps.storage, msg.id)?;
+    let config = CONFIG.load(deps.storage)?;
+    config.assert_not_paused(PauseAction::Execute)?;
+
+    if info.sender != position.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if position.has_ask_deposits() {
+        return Err(ContractError::AskDeposited {
+            action: "accept_counter_offer".to_string(),
+        });
+    }
+
+    let proposer = deps.api.addr_validate(&msg.proposer)?;
+    let counter_offer = COUNTER_OFFERS.load(deps.storage, (msg.id, &proposer))?;
+    COUNTER_OFFERS.remove(deps.storage, (msg.id, &proposer));
+
+    position.ask = counter_offer.ask;
+
+    position.active(&env, &proposer)?;
+
+    let msgs_release = release_otc_items(&env, &mut position, &config.fee_collector)?;
+
+    let attrs_close = after_action(deps.branch(), &env, &mut position)?;
+
+    let msgs_nft = mint_position_nfts(deps, &mut position, &config)?;
+
+    Ok(Response::new()
+        .add_messages(msgs_release)
+        .add_messages(msgs_nft)
+        .add_attribute("action", "accept_counter_offer")
         .add_attribute("otc_id", msg.id.to_string())
+        .add_attribute("proposer", proposer)
         .add_attributes(attrs_close))
 }
 
+/// Refund a counter offer, rejected by the position owner or withdrawn by the proposer
+pub fn run_reject_counter_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: CounterOfferActionMsg,
+) -> ContractResponse {
+    let position = positions().load(deps.storage, msg.id)?;
+    let proposer = deps.api.addr_validate(&msg.proposer)?;
+
+    if info.sender != position.owner && info.sender != proposer {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let counter_offer = COUNTER_OFFERS.load(deps.storage, (msg.id, &proposer))?;
+    COUNTER_OFFERS.remove(deps.storage, (msg.id, &proposer));
+
+    let msgs_refund =
+        build_send_otc_info_items(&env, &deposited_items(&counter_offer.ask), &proposer)?;
+
+    Ok(Response::new()
+        .add_messages(msgs_refund)
+        .add_attribute(
+            "action",
+            if info.sender == proposer {
+                "withdraw_counter_offer"
+            } else {
+                "reject_counter_offer"
+            },
+        )
+        .add_attribute("otc_id", msg.id.to_string())
+        .add_attribute("proposer", proposer))
+}
+
+/// Handle `Cw20` sent with `Cw20ExecuteMsg::Send`, the received tokens are used as deposit for the hook msg
+pub fn run_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: Cw20ReceiveMsg,
+) -> ContractResponse {
+    let sender = deps.api.addr_validate(&msg.sender)?;
+    let deposit = Deposit {
+        coins: info.funds,
+        cw20: Some((info.sender, msg.amount)),
+        cw721: None,
+    };
+
+    match from_json(&msg.msg)? {
+        Cw20HookMsg::CreateOtc(msg) => run_create_otc(deps, env, sender, deposit, msg),
+        Cw20HookMsg::ExecuteOtc(msg) => run_execute_otc(deps, env, sender, deposit, msg),
+        Cw20HookMsg::DepositOtc(msg) => run_deposit_otc(deps, env, sender, deposit, msg),
+        Cw20HookMsg::UpdateOtc(msg) => run_update_otc(deps, env, sender, deposit, msg),
+        Cw20HookMsg::CounterOffer(msg) => run_counter_offer(deps, env, sender, deposit, msg),
+    }
+}
+
+/// Handle `Cw721` sent with `Cw721ExecuteMsg::SendNft`, the received nft is used as deposit for the hook msg
+pub fn run_receive_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: Cw721ReceiveMsg,
+) -> ContractResponse {
+    let sender = deps.api.addr_validate(&msg.sender)?;
+    let deposit = Deposit {
+        coins: info.funds,
+        cw20: None,
+        cw721: Some((info.sender, msg.token_id)),
+    };
+
+    match from_json(&msg.msg)? {
+        Cw721HookMsg::CreateOtc(msg) => run_create_otc(deps, env, sender, deposit, msg),
+        Cw721HookMsg::ExecuteOtc(msg) => run_execute_otc(deps, env, sender, deposit, msg),
+        Cw721HookMsg::DepositOtc(msg) => run_deposit_otc(deps, env, sender, deposit, msg),
+        Cw721HookMsg::UpdateOtc(msg) => run_update_otc(deps, env, sender, deposit, msg),
+        Cw721HookMsg::CounterOffer(msg) => run_counter_offer(deps, env, sender, deposit, msg),
+    }
+}
+
 pub fn run_claim_otc(
     deps: DepsMut,
     env: Env,
@@ -91,17 +705,40 @@ pub fn run_claim_otc(
     msg: ClaimOtcMsg,
 ) -> ContractResponse {
     let mut position = positions().load(deps.storage, msg.id)?;
+    let config = CONFIG.load(deps.storage)?;
+    config.assert_not_paused(PauseAction::Claim)?;
 
-    let msgs = if info.sender == position.owner {
-        send_otc_items(&env, &mut position.ask, &position.status, &info.sender)?
-    } else if info.sender == position.executor.clone().unwrap() {
-        send_otc_items(&env, &mut position.offer, &position.status, &info.sender)?
-    } else {
+    let recipient = msg
+        .recipient
+        .map(|val| deps.api.addr_validate(&val))
+        .transpose()?;
+
+    // Both sides are claimed if the sender holds both side tokens
+    let sides = claimer_sides(deps.as_ref(), &position, &info.sender);
+
+    if sides.is_empty() {
         return Err(ContractError::Unauthorized {});
-    };
+    }
+
+    let mut msgs = vec![];
+    for side in sides {
+        let to = recipient
+            .clone()
+            .unwrap_or(position.claim_receiver(&side, &info.sender));
+
+        let (items, status, fee_bps) = position.side_items_mut(&side);
+        msgs.append(&mut send_otc_items(
+            &env,
+            items,
+            status,
+            &to,
+            fee_bps,
+            &config.fee_collector,
+        )?);
+    }
 
     if msgs.is_empty() {
-        return Err(StdError::generic_err("Nothing to claim").into());
+        return Err(ContractError::NothingToClaim {});
     }
 
     let attrs_close = after_action(deps, &env, &mut position)?;
@@ -113,27 +750,486 @@ pub fn run_claim_otc(
         .add_attributes(attrs_close))
 }
 
+pub fn run_claim_many(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ClaimManyMsg,
+) -> ContractResponse {
+    let config = CONFIG.load(deps.storage)?;
+    config.assert_not_paused(PauseAction::Claim)?;
+
+    // Positions found through the indexes are skipped if the sender no longer holds their side token
+    let skip_unauthorized = msg.ids.is_none();
+
+    let limit = min(MAX_LIMIT, msg.limit.unwrap_or(DEFAULT_LIMIT));
+
+    let mut ids = match msg.ids {
+        Some(ids) => ids,
+        None => {
+            let status = QueryPositionsFilterStatus::Vesting.as_string();
+
+            // The first `limit` ids of each index include the first `limit` of the merged ids
+            let as_owner = get_multi_index_values(
+                deps.storage,
+                (info.sender.to_string(), status.clone()),
+                positions().idx.owner_status,
+                Order::Ascending,
+                None,
+                Some(limit),
+            )?;
+
+            let as_executor = get_executors_index_values(
+                deps.storage,
+                (info.sender.to_string(), status),
+                positions().idx.executor_status,
+                Order::Ascending,
+                None,
+                Some(limit),
+            )?;
+
+            as_owner
+                .into_iter()
+                .chain(as_executor)
+                .map(|(id, _)| id)
+                .collect()
+        }
+    };
+
+    // A position loaded twice would be claimed twice
+    ids.sort();
+    ids.dedup();
+
+    if skip_unauthorized {
+        ids.truncate(limit as usize);
+    } else if ids.len() > MAX_LIMIT as usize {
+        return Err(ContractError::TooManyPositions { max: MAX_LIMIT });
+    }
+
+    // Items to send grouped by receiver, the sender or the payout address of each position
+    let mut to_receivers: Vec<(Addr, Vec<OtcItemInfo>)> = vec![];
+    let mut to_fee_collector: Vec<OtcItemInfo> = vec![];
+    let mut claimed_ids: Vec<String> = vec![];
+    let mut attrs_close = vec![];
+
+    for id in ids {
+        let mut position = positions().load(deps.storage, id)?;
+
+        match position.status {
+            OtcPositionStatus::Vesting(..) => {}
+            // Fully claimed already
+            OtcPositionStatus::Executed(..) => continue,
+            _ => {
+                return Err(ContractError::InvalidStatus {
+                    action: "claim".to_string(),
+                    status: position.status.clone(),
+                })
+            }
+        }
+
+        let sides = claimer_sides(deps.as_ref(), &position, &info.sender);
+
+        if sides.is_empty() {
+            if skip_unauthorized {
+                continue;
+            }
+
+            return Err(ContractError::Unauthorized {});
+        }
+
+        let mut claimed = false;
+        for side in sides {
+            let receiver = position.claim_receiver(&side, &info.sender);
+
+            let (items, status, fee_bps) = position.side_items_mut(&side);
+            let (mut receiver_items, mut fee_items) =
+                claim_otc_items(&env, items, status, fee_bps)?;
+
+            if receiver_items.is_empty() && fee_items.is_empty() {
+                continue;
+            }
+
+            match to_receivers.iter_mut().find(|(addr, _)| *addr == receiver) {
+                Some((_, items)) => items.append(&mut receiver_items),
+                None => to_receivers.push((receiver, receiver_items)),
+            }
+            to_fee_collector.append(&mut fee_items);
+            claimed = true;
+        }
+
+        if !claimed {
+            continue;
+        }
+
+        claimed_ids.push(id.to_string());
+        attrs_close.append(&mut after_action(deps.branch(), &env, &mut position)?);
+    }
+
+    if claimed_ids.is_empty() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    let mut msgs = vec![];
+    for (receiver, items) in to_receivers {
+        msgs.append(&mut build_send_otc_info_items(
+            &env,
+            &merge_otc_items_info(items)?,
+            &receiver,
+        )?);
+    }
+    msgs.append(&mut build_send_otc_info_items(
+        &env,
+        &merge_otc_items_info(to_fee_collector)?,
+        &config.fee_collector,
+    )?);
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "claim_many")
+        .add_attribute("ids", claimed_ids.join(","))
+        .add_attributes(attrs_close))
+}
+
+pub fn run_set_payout_address(
+    deps: DepsMut,
+    info: MessageInfo,
+    msg: SetPayoutAddressMsg,
+) -> ContractResponse {
+    let mut position = positions().load(deps.storage, msg.id)?;
+
+    if matches!(
+        position.status,
+        OtcPositionStatus::Executed(..)
+            | OtcPositionStatus::Expired(..)
+            | OtcPositionStatus::Cancelled(..)
+    ) {
+        return Err(ContractError::InvalidStatus {
+            action: "set_payout_address".to_string(),
+            status: position.status.clone(),
+        });
+    }
+
+    if position.position_nft.is_some() {
+        return Err(ContractError::PositionTokenized { id: position.id });
+    }
+
+    let address = msg
+        .address
+        .map(|val| deps.api.addr_validate(&val))
+        .transpose()?;
+
+    let side = if info.sender == position.owner {
+        position.owner_payout = address.clone();
+        "owner"
+    } else if position.executor.as_ref() == Some(&info.sender) {
+        position.executor_payout = address.clone();
+        "executor"
+    } else {
+        return Err(ContractError::Unauthorized {});
+    };
+
+    positions().save(deps.storage, position.id, &position)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_payout_address")
+        .add_attribute("id", msg.id.to_string())
+        .add_attribute("side", side)
+        .add_attribute(
+            "address",
+            address.map(|val| val.to_string()).unwrap_or_default(),
+        ))
+}
+
+pub fn run_transfer_position_side(
+    deps: DepsMut,
+    info: MessageInfo,
+    msg: TransferPositionSideMsg,
+) -> ContractResponse {
+    let mut position = positions().load(deps.storage, msg.id)?;
+
+    assert_side_transferable(&position, &msg.side)?;
+
+    if !position.is_side_holder(&msg.side, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let new_address = deps.api.addr_validate(&msg.new_address)?;
+    let require_acceptance = msg.require_acceptance.unwrap_or(false);
+
+    if require_acceptance {
+        match msg.side {
+            PositionSide::Owner => position.pending_owner = Some(new_address.clone()),
+            PositionSide::Executor => position.pending_executor = Some(new_address.clone()),
+        }
+    } else {
+        position.transfer_side(&msg.side, new_address.clone());
+    }
+
+    // The indexes are updated from the previous and the new state of the position
+    positions().save(deps.storage, position.id, &position)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "transfer_position_side")
+        .add_attribute("id", msg.id.to_string())
+        .add_attribute("side", msg.side.to_string())
+        .add_attribute("new_address", new_address)
+        .add_attribute("pending", require_acceptance.to_string()))
+}
+
+pub fn run_accept_position_side(
+    deps: DepsMut,
+    info: MessageInfo,
+    msg: AcceptPositionSideMsg,
+) -> ContractResponse {
+    let mut position = positions().load(deps.storage, msg.id)?;
+
+    assert_side_transferable(&position, &msg.side)?;
+
+    let pending = match msg.side {
+        PositionSide::Owner => &position.pending_owner,
+        PositionSide::Executor => &position.pending_executor,
+    };
+
+    if pending.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    position.transfer_side(&msg.side, info.sender.clone());
+
+    positions().save(deps.storage, position.id, &position)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_position_side")
+        .add_attribute("id", msg.id.to_string())
+        .add_attribute("side", msg.side.to_string())
+        .add_attribute("new_address", info.sender))
+}
+
+/// The owner side can be transferred while the position is open, the executor side once it's vesting
+fn assert_side_transferable(position: &OtcPosition, side: &PositionSide) -> ContractResult<()> {
+    if position.position_nft.is_some() {
+        return Err(ContractError::PositionTokenized { id: position.id });
+    }
+
+    match (side, &position.status) {
+        (
+            _,
+            OtcPositionStatus::Executed(..)
+            | OtcPositionStatus::Expired(..)
+            | OtcPositionStatus::Cancelled(..),
+        )
+        | (PositionSide::Executor, OtcPositionStatus::Funding | OtcPositionStatus::Pending) => {
+            Err(ContractError::InvalidStatus {
+                action: format!("transfer_{side}_side"),
+                status: position.status.clone(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
 pub fn run_cancel_otc(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: CancelOtcMsg,
 ) -> ContractResponse {
-    let position = positions().load(deps.storage, msg.id)?;
+    CONFIG
+        .load(deps.storage)?
+        .assert_not_paused(PauseAction::Cancel)?;
+
+    let mut position = positions().load(deps.storage, msg.id)?;
+
+    // The executor can withdraw the `ask` items deposited so far
+    if position.executor.as_ref() == Some(&info.sender)
+        && position.status.is_in_pending()
+        && position.has_ask_deposits()
+    {
+        let msgs_to_executor =
+            build_send_otc_info_items(&env, &deposited_items(&position.ask), &info.sender)?;
+
+        for item in position.ask.iter_mut() {
+            item.deposited = false;
+        }
+
+        positions().save(deps.storage, msg.id, &position)?;
+
+        return Ok(Response::new()
+            .add_messages(msgs_to_executor)
+            .add_attribute("action", "withdraw_otc_deposit")
+            .add_attribute("id", msg.id.to_string()));
+    }
 
     if info.sender != position.owner {
         return Err(ContractError::Unauthorized {});
     }
 
-    if !position.status.is_in_pending() {
-        return Err(StdError::generic_err("Can't cancel a position non in pending status").into());
+    if !position.status.is_in_pending() && !position.status.is_in_funding() {
+        return Err(ContractError::InvalidStatus {
+            action: "cancel".to_string(),
+            status: position.status.clone(),
+        });
     }
-    let msgs_to_owner = cancel_otc(&env, &position)?;
 
-    positions().remove(deps.storage, msg.id)?;
+    let msgs_refund = cancel_otc(&env, &position)?;
+
+    let action = if position.is_expired(&env.block) {
+        position.status = OtcPositionStatus::Expired(env.block.time.seconds());
+        "reclaim_expired_otc"
+    } else {
+        position.status = OtcPositionStatus::Cancelled(env.block.time.seconds());
+        "cancel_otc"
+    };
+
+    positions().save(deps.storage, msg.id, &position)?;
 
     Ok(Response::new()
-        .add_messages(msgs_to_owner)
-        .add_attribute("action", "cancel_otc")
+        .add_messages(msgs_refund)
+        .add_attribute("action", action)
         .add_attribute("id", msg.id.to_string()))
 }
+
+pub fn run_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    msg: UpdateConfigMsg,
+) -> ContractResponse {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut response = Response::new().add_attribute("action", "update_config");
+
+    if let Some(fee) = msg.fee {
+        Config::validate_fee(deps.as_ref(), &fee)?;
+        config.fee = fee;
+        response = response.add_attribute("fee", "updated");
+    }
+
+    if let Some(fee_collector) = msg.fee_collector {
+        config.fee_collector = deps.api.addr_validate(&fee_collector)?;
+        response = response.add_attribute("fee_collector", fee_collector);
+    }
+
+    if let Some(strict_funds) = msg.strict_funds {
+        config.strict_funds = strict_funds;
+        response = response.add_attribute("strict_funds", strict_funds.to_string());
+    }
+
+    if let Some(trade_fee) = msg.trade_fee {
+        trade_fee.validate()?;
+        response = response
+            .add_attribute("maker_bps", trade_fee.maker_bps.to_string())
+            .add_attribute("taker_bps", trade_fee.taker_bps.to_string());
+        config.trade_fee = trade_fee;
+    }
+
+    if let Some(position_nft) = msg.position_nft {
+        config.position_nft = Some(deps.api.addr_validate(&position_nft)?);
+        response = response.add_attribute("position_nft", position_nft);
+    }
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(response)
+}
+
+pub fn run_set_pause(deps: DepsMut, info: MessageInfo, msg: SetPauseMsg) -> ContractResponse {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    let is_owner = info.sender == config.owner;
+
+    if !is_owner && config.guardian.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut response = Response::new().add_attribute("action", "set_pause");
+
+    for (action, paused) in [
+        (PauseAction::Create, msg.create),
+        (PauseAction::Execute, msg.execute),
+        (PauseAction::Claim, msg.claim),
+        (PauseAction::Cancel, msg.cancel),
+    ] {
+        if let Some(paused) = paused {
+            // The guardian can only pause
+            if !paused && !is_owner {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            config.pause.set(&action, paused);
+            response = response.add_attribute(action.to_string(), paused.to_string());
+        }
+    }
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(response)
+}
+
+pub fn run_set_guardian(deps: DepsMut, info: MessageInfo, msg: SetGuardianMsg) -> ContractResponse {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.guardian = msg
+        .guardian
+        .map(|val| deps.api.addr_validate(&val))
+        .transpose()?;
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_guardian")
+        .add_attribute(
+            "guardian",
+            config.guardian.unwrap_or(Addr::unchecked("undefined")),
+        ))
+}
+
+pub fn run_propose_new_owner(
+    deps: DepsMut,
+    info: MessageInfo,
+    msg: ProposeNewOwnerMsg,
+) -> ContractResponse {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.pending_owner = msg
+        .owner
+        .map(|val| deps.api.addr_validate(&val))
+        .transpose()?;
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "propose_new_owner")
+        .add_attribute(
+            "pending_owner",
+            config.pending_owner.unwrap_or(Addr::unchecked("undefined")),
+        ))
+}
+
+pub fn run_accept_ownership(deps: DepsMut, info: MessageInfo) -> ContractResponse {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    if config.pending_owner.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.owner = info.sender;
+    config.pending_owner = None;
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "accept_ownership")
+        .add_attribute("owner", config.owner))
+}
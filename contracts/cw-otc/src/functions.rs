@@ -2,92 +2,558 @@ use std::cmp::min;
 
 use cosmwasm_schema::serde::{de::DeserializeOwned, Serialize};
 use cosmwasm_std::{
-    attr, Addr, Attribute, Coin, CosmosMsg, DepsMut, Env, Order, StdError, StdResult, Storage,
-    Uint128,
+    attr, wasm_execute, Addr, Attribute, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Empty,
+    Env, Order, StdResult, Storage, Uint128,
+};
+use cw20::Cw20ExecuteMsg;
+use cw721::{Cw721ExecuteMsg, Cw721QueryMsg, OwnerOfResponse};
+use cw_otc_common::{
+    definitions::{
+        Config, CounterOffer, OtcItem, OtcItemInfo, OtcPosition, OtcPositionStatus, PartialFill,
+        PositionSide, TradeFee,
+    },
+    error::{ContractError, ContractResult},
 };
-use cw_otc_common::definitions::{OtcItem, OtcItemInfo, OtcPosition, OtcPositionStatus};
 use cw_storage_plus::{
     Bound, IndexList, IndexedMap, KeyDeserialize, MultiIndex, Prefixer, PrimaryKey,
 };
 
-use crate::state::positions;
+use crate::state::{positions, ExecutorsIndex, COUNTER_OFFERS};
+
+pub const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_LIMIT: u32 = 30;
+
+/// Assets sent to the contract along with the current message
+pub struct Deposit {
+    pub coins: Vec<Coin>,
+    /// `Cw20` tokens received through `ExecuteMsg::Receive`
+    pub cw20: Option<(Addr, Uint128)>,
+    /// `Cw721` token received through `ExecuteMsg::ReceiveNft`
+    pub cw721: Option<(Addr, String)>,
+}
+
+impl Deposit {
+    pub fn from_funds(funds: Vec<Coin>) -> Deposit {
+        Deposit {
+            coins: funds,
+            cw20: None,
+            cw721: None,
+        }
+    }
+
+    /// Take the received `Cw721`, returns `false` if `contract` and `token_id` are not the ones received
+    fn take_cw721(&mut self, contract: &Addr, token_id: &str) -> bool {
+        match &self.cw721 {
+            Some((received, received_id)) if received == contract && received_id == token_id => {
+                self.cw721 = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Take `amount` of `denom` from the received coins, returns `false` if not enough was received
+    fn take_coin(&mut self, denom: &str, amount: Uint128) -> bool {
+        match self.coins.iter_mut().find(|c| c.denom == denom) {
+            Some(coin) if coin.amount >= amount => {
+                coin.amount -= amount;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Take `amount` from the received `Cw20`, returns `false` if `contract` is not the one received
+    /// or not enough was received
+    fn take_cw20(&mut self, contract: &Addr, amount: Uint128) -> bool {
+        match &mut self.cw20 {
+            Some((received, available)) if received == contract && amount.le(available) => {
+                *available -= amount;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Reject any coin, `Cw20` or `Cw721` not required by `items`
+    pub fn assert_expected<'a>(
+        &self,
+        items: impl Iterator<Item = &'a OtcItemInfo> + Clone,
+    ) -> ContractResult<()> {
+        for coin in &self.coins {
+            let expected = items.clone().any(
+                |item| matches!(item, OtcItemInfo::Token { denom, .. } if denom == &coin.denom),
+            );
+
+            if !expected {
+                return Err(ContractError::UnexpectedDenom {
+                    denom: coin.denom.clone(),
+                });
+            }
+        }
+
+        if let Some((received, _)) = &self.cw20 {
+            let expected = items.clone().any(
+                |item| matches!(item, OtcItemInfo::Cw20 { contract, .. } if contract == received),
+            );
 
-const DEFAULT_LIMIT: u32 = 10;
-const MAX_LIMIT: u32 = 30;
+            if !expected {
+                return Err(ContractError::UnexpectedCw20 {
+                    contract: received.to_string(),
+                });
+            }
+        }
+
+        if let Some((received, received_id)) = &self.cw721 {
+            let expected = items.clone().any(|item| match item {
+                OtcItemInfo::Cw721 { contract, token_id } => {
+                    contract == received && token_id == received_id
+                }
+                _ => false,
+            });
+
+            if !expected {
+                return Err(ContractError::UnexpectedNft {
+                    contract: received.to_string(),
+                    token_id: received_id.to_string(),
+                });
+            }
+        }
 
+        Ok(())
+    }
+}
+
+/// Escrow every `items` not yet deposited, see `deposit_otc_items`
 pub fn collect_otc_items(
+    deps: Deps,
     env: &Env,
-    items: &Vec<OtcItem>,
-    sender: Addr,
-    funds: Vec<Coin>,
-) -> StdResult<(Vec<CosmosMsg>, Vec<Coin>)> {
-    let coins = assert_received_funds(
-        &items.iter().map(|val| val.item_info.clone()).collect(),
-        funds,
-    )?;
+    items: &mut [OtcItem],
+    sender: &Addr,
+    deposit: Deposit,
+) -> ContractResult<(Vec<CosmosMsg>, Deposit)> {
+    let indexes: Vec<u32> = (0..items.len() as u32)
+        .filter(|index| !items[*index as usize].deposited)
+        .collect();
+
+    deposit_otc_items(deps, env, items, Some(&indexes), sender, deposit)
+}
+
+/// Escrow the `items` at `indexes`, using first the assets in `deposit` and pulling the `Cw20` and `Cw721`
+/// not received from `sender`. If `indexes` is `None`, every item covered by `deposit` is escrowed and
+/// nothing is pulled. Returns the part of `deposit` not consumed
+pub fn deposit_otc_items(
+    deps: Deps,
+    env: &Env,
+    items: &mut [OtcItem],
+    indexes: Option<&[u32]>,
+    sender: &Addr,
+    mut deposit: Deposit,
+) -> ContractResult<(Vec<CosmosMsg>, Deposit)> {
+    let pull = indexes.is_some();
+    let indexes: Vec<usize> = match indexes {
+        Some(indexes) => indexes.iter().map(|index| *index as usize).collect(),
+        None => (0..items.len())
+            .filter(|index| !items[*index].deposited)
+            .collect(),
+    };
+
     let mut msgs: Vec<CosmosMsg> = vec![];
-    for item in items {
-        match &item.item_info {
-            OtcItemInfo::Cw20 { .. } | &OtcItemInfo::Cw721 { .. } => msgs.push(
+    for index in indexes {
+        let item = items
+            .get_mut(index)
+            .ok_or(ContractError::InvalidItemIndex {
+                index: index as u64,
+            })?;
+
+        if item.deposited {
+            return Err(ContractError::ItemAlreadyDeposited {
+                index: index as u64,
+            });
+        }
+
+        let received = match &item.item_info {
+            OtcItemInfo::Token { denom, amount } => deposit.take_coin(denom, *amount),
+            OtcItemInfo::Cw20 { contract, amount } => deposit.take_cw20(contract, *amount),
+            OtcItemInfo::Cw721 { contract, token_id } => deposit.take_cw721(contract, token_id),
+        };
+
+        if !received {
+            if !pull {
+                continue;
+            }
+
+            if let OtcItemInfo::Token { denom, amount } = &item.item_info {
+                let available = deposit
+                    .coins
+                    .iter()
+                    .find(|c| c.denom.eq(denom))
+                    .map(|c| c.amount)
+                    .unwrap_or_default();
+
+                return Err(ContractError::InsufficientFunds {
+                    denom: denom.to_string(),
+                    expected: *amount,
+                    received: available,
+                });
+            }
+
+            // TransferNft can't specify the owner, make sure the contract doesn't pull an escrowed nft
+            if let OtcItemInfo::Cw721 { contract, token_id } = &item.item_info {
+                if query_nft_owner(deps, contract, token_id)? != sender.as_str() {
+                    return Err(ContractError::NftNotOwned {
+                        token_id: token_id.to_string(),
+                        owner: sender.to_string(),
+                    });
+                }
+            }
+
+            msgs.push(
                 item.item_info
-                    .build_send_msg(env, &sender, &env.contract.address, None)?,
-            ),
-            _ => {}
+                    .build_send_msg(env, sender, &env.contract.address, None)?,
+            )
         }
+
+        item.deposited = true;
     }
 
-    Ok((msgs, coins))
+    Ok((msgs, deposit))
 }
 
-pub fn send_otc_items(
+/// Update the claimed amount of `items`, returns the items to send to the receiver and the `fee_bps` fee items
+pub fn claim_otc_items(
     env: &Env,
     items: &mut Vec<OtcItem>,
     position_status: &OtcPositionStatus,
-    to: &Addr,
-) -> StdResult<Vec<CosmosMsg>> {
-    let mut msgs: Vec<CosmosMsg> = vec![];
+    fee_bps: u16,
+) -> ContractResult<(Vec<OtcItemInfo>, Vec<OtcItemInfo>)> {
+    let mut to_receiver: Vec<OtcItemInfo> = vec![];
+    let mut to_fee_collector: Vec<OtcItemInfo> = vec![];
     for item in items {
         let amount = item.sendable_amount_and_update_claimed_amount(env, position_status)?;
 
         if amount > Uint128::zero() {
-            msgs.push(item.item_info.build_send_msg(
-                env,
-                &env.contract.address,
-                to,
-                Some(amount),
-            )?)
+            if !item.item_info.is_fungible() {
+                to_receiver.push(item.item_info.clone());
+                continue;
+            }
+
+            let fee = TradeFee::compute(amount, fee_bps);
+
+            if amount > fee {
+                to_receiver.push(item.item_info.with_amount(amount - fee)?)
+            }
+
+            if fee > Uint128::zero() {
+                to_fee_collector.push(item.item_info.with_amount(fee)?)
+            }
         }
     }
+    Ok((to_receiver, to_fee_collector))
+}
+
+/// Release the sendable amount of `items` to `to`, taking `fee_bps` of every fungible item for `fee_collector`
+pub fn send_otc_items(
+    env: &Env,
+    items: &mut Vec<OtcItem>,
+    position_status: &OtcPositionStatus,
+    to: &Addr,
+    fee_bps: u16,
+    fee_collector: &Addr,
+) -> ContractResult<Vec<CosmosMsg>> {
+    let (to_receiver, to_fee_collector) = claim_otc_items(env, items, position_status, fee_bps)?;
+
+    let mut msgs = build_send_otc_info_items(env, &to_receiver, to)?;
+    msgs.append(&mut build_send_otc_info_items(
+        env,
+        &to_fee_collector,
+        fee_collector,
+    )?);
+
+    Ok(msgs)
+}
+
+/// Release the items of a position just activated, `ask` to the owner and `offer` to the executor, or their payout addresses
+pub fn release_otc_items(
+    env: &Env,
+    position: &mut OtcPosition,
+    fee_collector: &Addr,
+) -> ContractResult<Vec<CosmosMsg>> {
+    let owner_receiver = position.owner_receiver().clone();
+    let executor_receiver = position.executor_receiver().unwrap().clone();
+
+    let mut msgs = send_otc_items(
+        env,
+        &mut position.ask,
+        &position.status,
+        &owner_receiver,
+        position.trade_fee.maker_bps,
+        fee_collector,
+    )?;
+
+    msgs.append(&mut send_otc_items(
+        env,
+        &mut position.offer,
+        &position.status,
+        &executor_receiver,
+        position.trade_fee.taker_bps,
+        fee_collector,
+    )?);
+
     Ok(msgs)
 }
 
+/// Build the fee msgs. Native fee is taken from `deposit`, `Cw20` and `Cw721` fee from `deposit` if received
+/// else they are pulled from `payer`
 pub fn send_fee(
+    deps: Deps,
     env: &Env,
     items_info: &Vec<OtcItemInfo>,
     fee_collector: &Addr,
-    funds: Vec<Coin>,
-) -> StdResult<Vec<CosmosMsg>> {
-    assert_received_funds(items_info, funds)?;
-    build_send_otc_info_items(env, items_info, fee_collector)
+    payer: &Addr,
+    mut deposit: Deposit,
+) -> ContractResult<(Vec<CosmosMsg>, Deposit)> {
+    deposit.coins = assert_received_funds(items_info, deposit.coins)?;
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    for item_info in items_info {
+        let sender = match item_info {
+            OtcItemInfo::Token { .. } => &env.contract.address,
+            OtcItemInfo::Cw20 { contract, amount } => {
+                if deposit.take_cw20(contract, *amount) {
+                    &env.contract.address
+                } else {
+                    payer
+                }
+            }
+            OtcItemInfo::Cw721 { contract, token_id } => {
+                if deposit.take_cw721(contract, token_id) {
+                    msgs.push(item_info.build_send_msg(
+                        env,
+                        &env.contract.address,
+                        fee_collector,
+                        None,
+                    )?);
+                    continue;
+                }
+
+                // TransferNft can't specify the owner, make sure the contract doesn't send an escrowed nft
+                if query_nft_owner(deps, contract, token_id)? != payer.as_str() {
+                    return Err(ContractError::FeeNftNotOwned {
+                        token_id: token_id.to_string(),
+                        payer: payer.to_string(),
+                    });
+                }
+
+                payer
+            }
+        };
+
+        msgs.push(item_info.build_send_msg(env, sender, fee_collector, None)?)
+    }
+
+    Ok((msgs, deposit))
 }
 
-pub fn cancel_otc(env: &Env, position: &OtcPosition) -> StdResult<Vec<CosmosMsg>> {
-    build_send_otc_info_items(
-        env,
-        &position
-            .offer
-            .iter()
-            .map(|val| val.item_info.clone())
-            .collect(),
-        &position.owner,
-    )
+fn query_nft_owner(deps: Deps, contract: &Addr, token_id: &str) -> StdResult<String> {
+    let owner: OwnerOfResponse = deps.querier.query_wasm_smart(
+        contract,
+        &Cw721QueryMsg::OwnerOf {
+            token_id: token_id.to_string(),
+            include_expired: None,
+        },
+    )?;
+
+    Ok(owner.owner)
+}
+
+/// Send back to `to` the part of `deposit` left after deposit and fee
+pub fn refund_deposit(to: &Addr, deposit: Deposit) -> ContractResult<Vec<CosmosMsg>> {
+    let mut msgs: Vec<CosmosMsg> = vec![];
+
+    let coins: Vec<Coin> = deposit
+        .coins
+        .into_iter()
+        .filter(|c| !c.amount.is_zero())
+        .collect();
+
+    if !coins.is_empty() {
+        msgs.push(
+            BankMsg::Send {
+                to_address: to.to_string(),
+                amount: coins,
+            }
+            .into(),
+        )
+    }
+
+    if let Some((contract, amount)) = deposit.cw20 {
+        if !amount.is_zero() {
+            msgs.push(
+                wasm_execute(
+                    contract,
+                    &Cw20ExecuteMsg::Transfer {
+                        recipient: to.to_string(),
+                        amount,
+                    },
+                    vec![],
+                )?
+                .into(),
+            )
+        }
+    }
+
+    if let Some((contract, token_id)) = deposit.cw721 {
+        msgs.push(
+            wasm_execute(
+                contract,
+                &Cw721ExecuteMsg::TransferNft {
+                    recipient: to.to_string(),
+                    token_id,
+                },
+                vec![],
+            )?
+            .into(),
+        )
+    }
+
+    Ok(msgs)
+}
+
+/// Refund the deposited `offer` items, or the part not yet filled, to the owner and the deposited `ask` items
+/// to the executor
+pub fn cancel_otc(env: &Env, position: &OtcPosition) -> ContractResult<Vec<CosmosMsg>> {
+    let offer = match &position.partial_fill {
+        Some(partial_fill) => {
+            let mut offer = vec![];
+            for item_info in deposited_items(&position.offer) {
+                let amount = partial_fill.remaining_amount(item_info.get_amount());
+
+                if !amount.is_zero() {
+                    offer.push(item_info.with_amount(amount)?)
+                }
+            }
+            offer
+        }
+        None => deposited_items(&position.offer),
+    };
+
+    let mut msgs = build_send_otc_info_items(env, &offer, &position.owner)?;
+
+    if let Some(executor) = &position.executor {
+        msgs.append(&mut build_send_otc_info_items(
+            env,
+            &deposited_items(&position.ask),
+            executor,
+        )?);
+    }
+
+    Ok(msgs)
+}
+
+/// Portion of `items` exchanged by a fill moving `partial_fill` to `filled`.
+/// Rejects the fill if any item rounds to 0, otherwise one side could be exchanged for nothing
+pub fn fill_otc_items(
+    items: &[OtcItem],
+    partial_fill: &PartialFill,
+    filled: Decimal,
+) -> ContractResult<Vec<OtcItem>> {
+    let mut fill_items = vec![];
+
+    for item in items {
+        let amount = partial_fill.fill_amount(item.item_info.get_amount(), filled);
+
+        if amount.is_zero() {
+            return Err(ContractError::FillTooSmall {
+                fill: filled - partial_fill.filled,
+            });
+        }
+
+        fill_items.push(OtcItem {
+            item_info: item.item_info.with_amount(amount)?,
+            vesting_info: None,
+            deposited: item.deposited,
+        })
+    }
+
+    Ok(fill_items)
+}
+
+/// Sum the amounts of the `items` referring to the same asset
+fn merge_otc_items(items: &[OtcItem]) -> ContractResult<Vec<OtcItemInfo>> {
+    merge_otc_items_info(items.iter().map(|val| val.item_info.clone()))
+}
+
+/// Sum the amounts of the `items_info` referring to the same asset, so each asset is sent with a single msg
+pub fn merge_otc_items_info(
+    items_info: impl IntoIterator<Item = OtcItemInfo>,
+) -> ContractResult<Vec<OtcItemInfo>> {
+    let mut merged: Vec<OtcItemInfo> = vec![];
+    for item_info in items_info {
+        match merged.iter_mut().find(|val| val.is_same_asset(&item_info)) {
+            Some(val) if val.is_fungible() => {
+                *val = val.with_amount(val.get_amount() + item_info.get_amount())?
+            }
+            Some(val) => return Err(ContractError::DuplicatedItem { item: val.clone() }),
+            None => merged.push(item_info),
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Compare the assets of `current` and `updated` items, returns the items to deposit and the ones to refund
+pub fn diff_otc_items(
+    current: &[OtcItem],
+    updated: &[OtcItem],
+) -> ContractResult<(Vec<OtcItem>, Vec<OtcItemInfo>)> {
+    let current = merge_otc_items(current)?;
+    let updated = merge_otc_items(updated)?;
+
+    let delta = |from: &[OtcItemInfo], to: &[OtcItemInfo]| -> ContractResult<Vec<OtcItemInfo>> {
+        let mut items = vec![];
+        for item in to {
+            let amount = from
+                .iter()
+                .find(|val| val.is_same_asset(item))
+                .map(|val| val.get_amount())
+                .unwrap_or_default();
+
+            if item.get_amount() > amount {
+                items.push(if item.is_fungible() {
+                    item.with_amount(item.get_amount() - amount)?
+                } else {
+                    item.clone()
+                });
+            }
+        }
+        Ok(items)
+    };
+
+    let to_deposit = delta(&current, &updated)?
+        .into_iter()
+        .map(|item_info| OtcItem {
+            item_info,
+            vesting_info: None,
+            deposited: false,
+        })
+        .collect();
+
+    Ok((to_deposit, delta(&updated, &current)?))
+}
+
+pub fn deposited_items(items: &[OtcItem]) -> Vec<OtcItemInfo> {
+    items
+        .iter()
+        .filter(|val| val.deposited)
+        .map(|val| val.item_info.clone())
+        .collect()
 }
 
 pub fn build_send_otc_info_items(
     env: &Env,
     items_info: &Vec<OtcItemInfo>,
     to: &Addr,
-) -> StdResult<Vec<CosmosMsg>> {
+) -> ContractResult<Vec<CosmosMsg>> {
     let mut msgs: Vec<CosmosMsg> = vec![];
     for item_info in items_info {
         msgs.push(item_info.build_send_msg(env, &env.contract.address, to, None)?)
@@ -95,35 +561,41 @@ pub fn build_send_otc_info_items(
     Ok(msgs)
 }
 
-pub fn assert_received_funds(items: &Vec<OtcItemInfo>, funds: Vec<Coin>) -> StdResult<Vec<Coin>> {
-    let mut coins = vec![];
+/// Deduct the native `items` from `funds`, returning the coins not consumed
+pub fn assert_received_funds(
+    items: &Vec<OtcItemInfo>,
+    funds: Vec<Coin>,
+) -> ContractResult<Vec<Coin>> {
+    let mut coins = funds;
 
     for item in items {
         if let OtcItemInfo::Token { denom, amount } = &item {
-            let available_amount = funds
-                .iter()
-                .find(|c| c.denom.eq(denom))
-                .ok_or(StdError::generic_err(format!("Coin not received {denom}")))?
-                .amount;
+            let coin = coins.iter_mut().find(|c| c.denom.eq(denom)).ok_or(
+                ContractError::CoinNotReceived {
+                    denom: denom.to_string(),
+                },
+            )?;
 
-            if amount.gt(&available_amount) {
-                return Err(StdError::generic_err(format!(
-                    "Amount received for {denom} is to low: expected: {amount}, received: {amount}"
-                )));
+            if amount.gt(&coin.amount) {
+                return Err(ContractError::InsufficientFunds {
+                    denom: denom.to_string(),
+                    expected: *amount,
+                    received: coin.amount,
+                });
             }
 
-            coins.push(Coin::new((available_amount - amount).u128(), denom));
+            coin.amount -= amount;
         }
     }
 
-    Ok(coins)
+    Ok(coins.into_iter().filter(|c| !c.amount.is_zero()).collect())
 }
 
 pub fn after_action(
     deps: DepsMut,
     env: &Env,
     position: &mut OtcPosition,
-) -> StdResult<Vec<Attribute>> {
+) -> ContractResult<Vec<Attribute>> {
     let position_pre = position.status.as_string_ref();
 
     position.try_close(env)?;
@@ -131,10 +603,14 @@ pub fn after_action(
     let mut attributes: Vec<Attribute> = vec![];
 
     match position.status {
-        OtcPositionStatus::Pending => {
-            return Err(StdError::generic_err(
-                "Position should be Executed or Vesting",
-            ))
+        OtcPositionStatus::Funding
+        | OtcPositionStatus::Pending
+        | OtcPositionStatus::Expired(_)
+        | OtcPositionStatus::Cancelled(_) => {
+            return Err(ContractError::InvalidStatus {
+                action: "release".to_string(),
+                status: position.status.clone(),
+            })
         }
         OtcPositionStatus::Vesting(_) | OtcPositionStatus::Executed(_) => {
             positions().save(deps.storage, position.id, position)?;
@@ -151,6 +627,60 @@ pub fn after_action(
     Ok(attributes)
 }
 
+/// Mint a `Config.position_nft` token for each side of a position still in `Vesting` after its execution
+pub fn mint_position_nfts(
+    deps: DepsMut,
+    position: &mut OtcPosition,
+    config: &Config,
+) -> ContractResult<Vec<CosmosMsg>> {
+    let contract = match (&config.position_nft, &position.status) {
+        (Some(contract), OtcPositionStatus::Vesting(..)) => contract,
+        _ => return Ok(vec![]),
+    };
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    for (side, owner) in [
+        (PositionSide::Owner, &position.owner),
+        (PositionSide::Executor, position.executor.as_ref().unwrap()),
+    ] {
+        msgs.push(
+            wasm_execute(
+                contract,
+                &cw721_base::ExecuteMsg::<Empty, Empty>::Mint {
+                    token_id: side.token_id(position.id),
+                    owner: owner.to_string(),
+                    token_uri: None,
+                    extension: Empty {},
+                },
+                vec![],
+            )?
+            .into(),
+        );
+    }
+
+    position.position_nft = Some(contract.clone());
+    positions().save(deps.storage, position.id, position)?;
+
+    Ok(msgs)
+}
+
+/// Sides of `position` `sender` is entitled to claim, as holder of the side tokens if the position is tokenized.
+/// A side token that can't be queried, e.g. burned, is not held by `sender`
+pub fn claimer_sides(deps: Deps, position: &OtcPosition, sender: &Addr) -> Vec<PositionSide> {
+    let contract = match &position.position_nft {
+        Some(contract) => contract,
+        None => return position.sides_of(sender),
+    };
+
+    [PositionSide::Owner, PositionSide::Executor]
+        .into_iter()
+        .filter(|side| {
+            query_nft_owner(deps, contract, &side.token_id(position.id))
+                .is_ok_and(|owner| owner == sender.as_str())
+        })
+        .collect()
+}
+
 /// Load the values of a `cw_storage_plus::IndexMap` of a sub `MultiIndex`, ordered by `Order::Ascending` or `Order::Descending`
 pub fn get_multi_index_values<
     'a,
@@ -178,6 +708,49 @@ pub fn get_multi_index_values<
         .collect())
 }
 
+pub fn get_executors_index_values<'a, IK: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize>(
+    storage: &dyn Storage,
+    key: IK,
+    index: ExecutorsIndex<'a, IK>,
+    order: Order,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, OtcPosition)>> {
+    let (min_b, max_b) = match order {
+        Order::Ascending => (start_after.map(Bound::exclusive), None),
+        Order::Descending => (None, start_after.map(Bound::exclusive)),
+    };
+
+    index
+        .prefix(key)
+        .keys(storage, min_b, max_b, order)
+        .take(min(MAX_LIMIT, limit.unwrap_or(DEFAULT_LIMIT)) as usize)
+        .map(|id| {
+            let id = id?;
+            Ok((id, positions().load(storage, id)?))
+        })
+        .collect()
+}
+
+pub fn get_counter_offers(
+    storage: &dyn Storage,
+    id: u64,
+    start_after: Option<&Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<CounterOffer>> {
+    COUNTER_OFFERS
+        .prefix(id)
+        .range(
+            storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(min(MAX_LIMIT, limit.unwrap_or(DEFAULT_LIMIT)) as usize)
+        .map(|item| item.map(|(_, val)| val))
+        .collect()
+}
+
 pub fn get_items<
     'a,
     T: Serialize + DeserializeOwned + Clone,
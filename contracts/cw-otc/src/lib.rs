@@ -2,6 +2,7 @@
 pub mod contract;
 mod execute;
 mod functions;
+mod migrations;
 mod query;
 mod response;
 mod state;
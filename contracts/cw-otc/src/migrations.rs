@@ -0,0 +1,232 @@
+use cosmwasm_std::{Order, StdResult, Storage};
+use cw2::ContractVersion;
+use cw_otc_common::{
+    definitions::{Config, OtcPosition},
+    error::{ContractError, ContractResult},
+};
+use cw_storage_plus::Map;
+
+use crate::state::CONFIG;
+
+/// Version assumed for the stores instantiated before the contract version was recorded
+pub const LEGACY_VERSION: &str = "0.1.0";
+
+/// Primary namespace of `positions()`.
+/// The index keys are unchanged by the migrations, so only the positions are rewritten
+const POSITIONS: Map<u64, OtcPosition> = Map::new("active_position");
+
+/// Check the `cw2` info of the store against the new contract and return the version to migrate from
+pub fn assert_migration(
+    stored: Option<ContractVersion>,
+    contract_name: &str,
+    contract_version: &str,
+) -> ContractResult<String> {
+    let Some(stored) = stored else {
+        return Ok(LEGACY_VERSION.to_string());
+    };
+
+    if stored.contract != contract_name {
+        return Err(ContractError::InvalidContractName {
+            expected: contract_name.to_string(),
+            received: stored.contract,
+        });
+    }
+
+    if parse_version(&stored.version)? > parse_version(contract_version)? {
+        return Err(ContractError::CannotDowngrade {
+            from: stored.version,
+            to: contract_version.to_string(),
+        });
+    }
+
+    Ok(stored.version)
+}
+
+/// Run in order every state migration newer than `from_version`
+pub fn migrate_state(storage: &mut dyn Storage, from_version: &str) -> ContractResult<()> {
+    if parse_version(from_version)? < (0, 2, 0) {
+        migrate_from_v0_1(storage)?;
+    }
+
+    Ok(())
+}
+
+/// `major.minor.patch`, pre-release and build metadata are not supported
+pub fn parse_version(version: &str) -> ContractResult<(u64, u64, u64)> {
+    let parts = version
+        .split('.')
+        .map(|val| val.parse::<u64>())
+        .collect::<Result<Vec<u64>, _>>()
+        .map_err(|_| ContractError::InvalidVersion {
+            version: version.to_string(),
+        })?;
+
+    match parts[..] {
+        [major, minor, patch] => Ok((major, minor, patch)),
+        _ => Err(ContractError::InvalidVersion {
+            version: version.to_string(),
+        }),
+    }
+}
+
+fn migrate_from_v0_1(storage: &mut dyn Storage) -> ContractResult<()> {
+    let config: Config = v0_1::CONFIG.load(storage)?.into();
+    CONFIG.save(storage, &config)?;
+
+    let legacy_positions = v0_1::POSITIONS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (id, position) in legacy_positions {
+        POSITIONS.save(storage, id, &position.into())?;
+    }
+
+    Ok(())
+}
+
+/// Schema of the `0.1.0` release
+pub mod v0_1 {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{Addr, Uint128};
+    use cw_otc_common::definitions::{
+        self, OtcItemInfo, PauseFlags, TradeFee, VestingClock, VestingSchedule, VestingStart,
+    };
+    use cw_storage_plus::{Item, Map};
+
+    pub const CONFIG: Item<Config> = Item::new("config");
+
+    pub const POSITIONS: Map<u64, OtcPosition> = Map::new("active_position");
+
+    #[cw_serde]
+    pub struct Config {
+        pub owner: Addr,
+        pub counter_otc: u64,
+        pub fee: Vec<OtcItemInfo>,
+        pub fee_collector: Addr,
+    }
+
+    #[cw_serde]
+    pub struct OtcItem {
+        pub item_info: OtcItemInfo,
+        pub vesting_info: Option<VestingInfo>,
+    }
+
+    #[cw_serde]
+    pub struct VestingInfo {
+        pub cliff: Option<u64>,
+        pub vesting: Option<u64>,
+        pub claimed: Uint128,
+    }
+
+    #[cw_serde]
+    pub struct OtcPosition {
+        pub id: u64,
+        pub owner: Addr,
+        pub executor: Option<Addr>,
+        pub offer: Vec<OtcItem>,
+        pub ask: Vec<OtcItem>,
+        pub creation_time: u64,
+        pub status: OtcPositionStatus,
+    }
+
+    #[cw_serde]
+    pub enum OtcPositionStatus {
+        Pending,
+        Vesting(u64),
+        Executed(u64),
+    }
+
+    impl From<Config> for definitions::Config {
+        fn from(value: Config) -> Self {
+            definitions::Config {
+                owner: value.owner,
+                pending_owner: None,
+                counter_otc: value.counter_otc,
+                fee: value.fee,
+                fee_collector: value.fee_collector,
+                strict_funds: false,
+                trade_fee: TradeFee::default(),
+                position_nft: None,
+                guardian: None,
+                pause: PauseFlags::default(),
+            }
+        }
+    }
+
+    impl OtcItem {
+        /// The `offer` of a pending position is fully escrowed.
+        /// On execution the items without vesting were released, the vesting ones stay escrowed until fully claimed
+        fn migrate(self, status: &OtcPositionStatus, is_offer: bool) -> definitions::OtcItem {
+            let deposited = match (status, &self.vesting_info) {
+                (OtcPositionStatus::Pending, _) => is_offer,
+                (OtcPositionStatus::Vesting(_), Some(vesting_info)) => {
+                    vesting_info.claimed < self.item_info.get_amount()
+                }
+                _ => false,
+            };
+
+            definitions::OtcItem {
+                item_info: self.item_info,
+                vesting_info: self.vesting_info.map(|val| definitions::VestingInfo {
+                    schedule: VestingSchedule::Linear {
+                        cliff: val.cliff,
+                        vesting: val.vesting,
+                    },
+                    clock: VestingClock::Time,
+                    start_at: None,
+                    claimed: val.claimed,
+                }),
+                deposited,
+            }
+        }
+    }
+
+    impl From<OtcPosition> for definitions::OtcPosition {
+        fn from(value: OtcPosition) -> Self {
+            let offer = value
+                .offer
+                .into_iter()
+                .map(|val| val.migrate(&value.status, true))
+                .collect();
+
+            let ask = value
+                .ask
+                .into_iter()
+                .map(|val| val.migrate(&value.status, false))
+                .collect();
+
+            definitions::OtcPosition {
+                id: value.id,
+                owner: value.owner,
+                executors: value.executor.clone().into_iter().collect(),
+                executor: value.executor,
+                offer,
+                ask,
+                creation_time: value.creation_time,
+                status: value.status.into(),
+                trade_fee: TradeFee::default(),
+                expires_at: None,
+                partial_fill: None,
+                owner_payout: None,
+                executor_payout: None,
+                position_nft: None,
+                pending_owner: None,
+                pending_executor: None,
+                fee_paid: false,
+            }
+        }
+    }
+
+    impl From<OtcPositionStatus> for definitions::OtcPositionStatus {
+        fn from(value: OtcPositionStatus) -> Self {
+            match value {
+                OtcPositionStatus::Pending => definitions::OtcPositionStatus::Pending,
+                // Legacy vesting is measured only in seconds, the height is never read
+                OtcPositionStatus::Vesting(time) => {
+                    definitions::OtcPositionStatus::Vesting(VestingStart { time, height: 0 })
+                }
+                OtcPositionStatus::Executed(time) => definitions::OtcPositionStatus::Executed(time),
+            }
+        }
+    }
+}
@@ -1,14 +1,21 @@
-use cosmwasm_std::{Deps, Order, StdError, StdResult};
+use cosmwasm_std::{Deps, Env, Order, StdError, StdResult, Timestamp};
 use cw_otc_common::{
-    definitions::OtcPosition,
-    msgs::{QueryPositionsFilter, QueryPositionsFilterOrder},
+    definitions::{Config, CounterOffer, OtcPosition, PositionSide},
+    msgs::{ClaimableItem, QueryPositionsFilter, QueryPositionsFilterOrder},
 };
 
 use crate::{
-    functions::{get_items, get_multi_index_values},
-    state::positions,
+    functions::{
+        claimer_sides, get_counter_offers, get_executors_index_values, get_items,
+        get_multi_index_values,
+    },
+    state::{positions, CONFIG},
 };
 
+pub fn qy_config(deps: Deps) -> StdResult<Config> {
+    CONFIG.load(deps.storage)
+}
+
 pub fn qy_position(deps: Deps, id: u64) -> StdResult<OtcPosition> {
     positions().load(deps.storage, id)
 }
@@ -36,7 +43,7 @@ pub fn qy_positions(
                 limit,
             ),
             // executor
-            (None, Some(executor), None) => get_multi_index_values(
+            (None, Some(executor), None) => get_executors_index_values(
                 deps.storage,
                 executor,
                 positions().idx.executor,
@@ -45,7 +52,7 @@ pub fn qy_positions(
                 limit,
             ),
             // executor-status
-            (None, Some(executor), Some(status)) => get_multi_index_values(
+            (None, Some(executor), Some(status)) => get_executors_index_values(
                 deps.storage,
                 (executor, status.as_string()),
                 positions().idx.executor_status,
@@ -72,7 +79,7 @@ pub fn qy_positions(
                 limit,
             ),
             // owner-executor
-            (Some(owner), Some(executor), None) => get_multi_index_values(
+            (Some(owner), Some(executor), None) => get_executors_index_values(
                 deps.storage,
                 (owner, executor),
                 positions().idx.owner_executor,
@@ -81,7 +88,7 @@ pub fn qy_positions(
                 limit,
             ),
             // owner-executor-status
-            (Some(owner), Some(executor), Some(status)) => get_multi_index_values(
+            (Some(owner), Some(executor), Some(status)) => get_executors_index_values(
                 deps.storage,
                 (owner, executor, status.as_string()),
                 positions().idx.owner_executor_status,
@@ -95,3 +102,60 @@ pub fn qy_positions(
     }
     .map(|val| val.into_iter().map(|(_, val)| val).collect())
 }
+
+pub fn qy_counter_offers(
+    deps: Deps,
+    id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<CounterOffer>> {
+    let start_after = start_after
+        .map(|val| deps.api.addr_validate(&val))
+        .transpose()?;
+
+    get_counter_offers(deps.storage, id, start_after.as_ref(), limit)
+}
+
+pub fn qy_claimable(
+    deps: Deps,
+    env: Env,
+    id: u64,
+    address: String,
+    at_time: Option<u64>,
+    at_height: Option<u64>,
+) -> StdResult<Vec<ClaimableItem>> {
+    let position = positions().load(deps.storage, id)?;
+    let address = deps.api.addr_validate(&address)?;
+
+    let mut block = env.block;
+    if let Some(at_time) = at_time {
+        block.time = Timestamp::from_seconds(at_time);
+    }
+    if let Some(at_height) = at_height {
+        block.height = at_height;
+    }
+
+    let sides = claimer_sides(deps, &position, &address);
+
+    if sides.is_empty() {
+        return Err(StdError::generic_err(format!(
+            "{address} is not a party of the position"
+        )));
+    }
+
+    let mut claimable = vec![];
+    for side in sides {
+        let (items, fee_bps) = match side {
+            PositionSide::Owner => (&position.ask, position.trade_fee.maker_bps),
+            PositionSide::Executor => (&position.offer, position.trade_fee.taker_bps),
+        };
+
+        claimable.extend(
+            items
+                .iter()
+                .map(|item| item.claimable(&position.status, &block, fee_bps)),
+        );
+    }
+
+    Ok(claimable)
+}
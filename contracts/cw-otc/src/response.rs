@@ -1,13 +1,5 @@
-use cosmwasm_std::{Response, StdError};
-use thiserror::Error;
+use cosmwasm_std::Response;
 
-pub type ContractResponse = Result<Response, ContractError>;
-
-#[derive(Error, Debug)]
-pub enum ContractError {
-    #[error("{0}")]
-    Std(#[from] StdError),
+pub use cw_otc_common::error::ContractError;
 
-    #[error("Unauthorized")]
-    Unauthorized {},
-}
+pub type ContractResponse = Result<Response, ContractError>;
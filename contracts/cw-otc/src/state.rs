@@ -1,19 +1,69 @@
-use cw_otc_common::definitions::{Config, OtcPosition};
-use cw_storage_plus::{index_list, IndexedMap, Item, MultiIndex};
+use cosmwasm_std::{Addr, Empty, StdResult, Storage};
+use cw_otc_common::definitions::{Config, CounterOffer, OtcPosition};
+use cw_storage_plus::{
+    index_list, Index, IndexedMap, Item, KeyDeserialize, Map, MultiIndex, Prefix, Prefixer,
+    PrimaryKey,
+};
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+pub const COUNTER_OFFERS: Map<(u64, &Addr), CounterOffer> = Map::new("counter_offers");
+
 pub type PositionMap<'a> = IndexedMap<'a, u64, OtcPosition, OtcPositionIndexer<'a>>;
 
 #[index_list(OtcPosition)]
 pub struct OtcPositionIndexer<'a> {
     pub owner: MultiIndex<'a, String, OtcPosition, u64>,
-    pub executor: MultiIndex<'a, String, OtcPosition, u64>,
-    pub owner_executor: MultiIndex<'a, (String, String), OtcPosition, u64>,
+    pub executor: ExecutorsIndex<'a, String>,
+    pub owner_executor: ExecutorsIndex<'a, (String, String)>,
     pub status: MultiIndex<'a, String, OtcPosition, u64>,
     pub owner_status: MultiIndex<'a, (String, String), OtcPosition, u64>,
-    pub executor_status: MultiIndex<'a, (String, String), OtcPosition, u64>,
-    pub owner_executor_status: MultiIndex<'a, (String, String, String), OtcPosition, u64>,
+    pub executor_status: ExecutorsIndex<'a, (String, String)>,
+    pub owner_executor_status: ExecutorsIndex<'a, (String, String, String)>,
+}
+
+/// Like `MultiIndex`, but a position is indexed under one key for each of its allowed executors.
+/// The keys are stored with the same layout as `MultiIndex`
+pub struct ExecutorsIndex<'a, IK> {
+    idx_fn: fn(&OtcPosition) -> Vec<IK>,
+    idx_map: Map<'a, (IK, u64), Empty>,
+}
+
+impl<'a, IK> ExecutorsIndex<'a, IK>
+where
+    IK: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+{
+    pub fn new(idx_fn: fn(&OtcPosition) -> Vec<IK>, idx_namespace: &'a str) -> Self {
+        ExecutorsIndex {
+            idx_fn,
+            idx_map: Map::new(idx_namespace),
+        }
+    }
+
+    pub fn prefix(&self, key: IK) -> Prefix<u64, Empty, u64> {
+        self.idx_map.prefix(key)
+    }
+}
+
+impl<'a, IK> Index<OtcPosition> for ExecutorsIndex<'a, IK>
+where
+    IK: PrimaryKey<'a> + Prefixer<'a> + KeyDeserialize,
+{
+    fn save(&self, store: &mut dyn Storage, _pk: &[u8], data: &OtcPosition) -> StdResult<()> {
+        for key in (self.idx_fn)(data) {
+            self.idx_map.save(store, (key, data.id), &Empty {})?;
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, store: &mut dyn Storage, _pk: &[u8], old_data: &OtcPosition) -> StdResult<()> {
+        for key in (self.idx_fn)(old_data) {
+            self.idx_map.remove(store, (key, old_data.id));
+        }
+
+        Ok(())
+    }
 }
 
 pub fn positions<'a>() -> PositionMap<'a> {
@@ -23,27 +73,14 @@ pub fn positions<'a>() -> PositionMap<'a> {
             "active_position",
             "active_position_owner",
         ),
-        executor: MultiIndex::new(
-            |_, val| {
-                val.executor
-                    .clone()
-                    .map(|val| val.to_string())
-                    .unwrap_or("".to_string())
-            },
-            "active_position",
-            "active_position_executor",
-        ),
-        owner_executor: MultiIndex::new(
-            |_, val| {
-                (
-                    val.owner.to_string(),
-                    val.executor
-                        .clone()
-                        .map(|val| val.to_string())
-                        .unwrap_or("".to_string()),
-                )
+        executor: ExecutorsIndex::new(|val| val.index_executors(), "active_position_executor"),
+        owner_executor: ExecutorsIndex::new(
+            |val| {
+                val.index_executors()
+                    .into_iter()
+                    .map(|executor| (val.owner.to_string(), executor))
+                    .collect()
             },
-            "active_position",
             "active_position_owner_executor",
         ),
         status: MultiIndex::new(
@@ -56,31 +93,22 @@ pub fn positions<'a>() -> PositionMap<'a> {
             "active_position",
             "active_position_owner_status",
         ),
-        executor_status: MultiIndex::new(
-            |_, val| {
-                (
-                    val.executor
-                        .clone()
-                        .map(|val| val.to_string())
-                        .unwrap_or("".to_string()),
-                    val.status.as_string_ref(),
-                )
+        executor_status: ExecutorsIndex::new(
+            |val| {
+                val.index_executors()
+                    .into_iter()
+                    .map(|executor| (executor, val.status.as_string_ref()))
+                    .collect()
             },
-            "active_position",
             "active_position_executor_status",
         ),
-        owner_executor_status: MultiIndex::new(
-            |_, val| {
-                (
-                    val.owner.to_string(),
-                    val.executor
-                        .clone()
-                        .map(|val| val.to_string())
-                        .unwrap_or("".to_string()),
-                    val.status.as_string_ref(),
-                )
+        owner_executor_status: ExecutorsIndex::new(
+            |val| {
+                val.index_executors()
+                    .into_iter()
+                    .map(|executor| (val.owner.to_string(), executor, val.status.as_string_ref()))
+                    .collect()
             },
-            "active_position",
             "active_position_owner_executor_status",
         ),
     };
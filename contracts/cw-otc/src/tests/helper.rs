@@ -1,11 +1,18 @@
+use std::{env, fs, process::Command, sync::OnceLock};
+
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin, Empty, StdResult, Uint128};
+use cosmwasm_std::{to_json_binary, Addr, Coin, Decimal, Empty, StdResult, Uint128};
 use cosmwasm_testing_util::{test_tube::FEE_DENOM, ExecuteResponse, MockResult};
 use cw20::{BalanceResponse, Cw20Coin};
 use cw721::OwnerOfResponse;
 use cw_otc_common::{
-    definitions::{OtcItem, OtcItemInfo, OtcPosition},
-    msgs::{CreateOtcMsg, ExecuteOtcMsg, OtcItemRegistration},
+    definitions::{Config, CounterOffer, OtcItem, OtcItemInfo, OtcPosition, PositionSide},
+    msgs::{
+        AcceptPositionSideMsg, CancelOtcMsg, ClaimManyMsg, ClaimOtcMsg, ClaimableItem,
+        CounterOfferActionMsg, CounterOfferMsg, CreateOtcMsg, DepositOtcMsg, ExecuteOtcMsg,
+        OtcItemRegistration, QueryPositionsFilter, SetGuardianMsg, SetPauseMsg,
+        SetPayoutAddressMsg, TransferPositionSideMsg, UpdateConfigMsg, UpdateOtcMsg,
+    },
 };
 
 use super::app_ext::{MergeCoin, TestMockApp};
@@ -54,8 +61,45 @@ impl<'a> Def<'a> {
     }
 }
 
+/// The otc contract built to wasm from the current source, once per test run, so the tests never run a stale binary.
+/// Built in its own target dir, the one of `cargo test` is locked while the tests run
+fn otc_wasm() -> &'static [u8] {
+    static OTC_WASM: OnceLock<Vec<u8>> = OnceLock::new();
+
+    OTC_WASM.get_or_init(|| {
+        let target_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/integration-wasm");
+
+        let output = Command::new(env::var("CARGO").unwrap_or("cargo".to_string()))
+            .args([
+                "build",
+                "--release",
+                "--lib",
+                "--target",
+                "wasm32-unknown-unknown",
+            ])
+            .arg("--manifest-path")
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+            .arg("--target-dir")
+            .arg(target_dir)
+            .env("RUSTFLAGS", "-C link-arg=-s")
+            .output()
+            .unwrap();
+
+        assert!(
+            output.status.success(),
+            "building the otc wasm failed, is the wasm32-unknown-unknown target installed?\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        fs::read(format!(
+            "{target_dir}/wasm32-unknown-unknown/release/cw_otc.wasm"
+        ))
+        .unwrap()
+    })
+}
+
 pub fn startup(app: &mut TestMockApp, def: &mut Def) {
-    let otc_code_id = app.upload(include_bytes!("./testdata/cw-otc.wasm"));
+    let otc_code_id = app.upload(otc_wasm());
     let cw20_code_id = app.upload(include_bytes!("./testdata/cw20-base.wasm"));
     let cw721_code_id = app.upload(include_bytes!("./testdata/cw721-base.wasm"));
 
@@ -70,6 +114,9 @@ pub fn startup(app: &mut TestMockApp, def: &mut Def) {
                 owner: def.owner.to_string(),
                 fee: def.otc_fee.clone(),
                 fee_collector: def.fee_collector.to_string(),
+                strict_funds: None,
+                trade_fee: None,
+                position_nft: None,
             },
             &[],
             "otc",
@@ -163,6 +210,22 @@ pub fn create_token(
     }
 }
 
+/// Cw721 collection minted by the otc contract, to be set as `Config.position_nft`
+pub fn create_position_nft(app: &mut TestMockApp, def: &mut Def) -> Addr {
+    app.instantiate(
+        def.code_id_cw721.unwrap(),
+        Addr::unchecked(def.owner),
+        &cw721_base::msg::InstantiateMsg {
+            name: "OtcPosition".to_string(),
+            symbol: "OTCP".to_string(),
+            minter: def.addr_otc.clone().unwrap().to_string(),
+        },
+        &[],
+        "OtcPosition",
+    )
+    .unwrap()
+}
+
 pub fn mint_token(
     app: &mut TestMockApp,
     def: &mut Def,
@@ -271,14 +334,214 @@ pub fn run_create_otc(
         Addr::unchecked(creator),
         def.addr_otc.clone().unwrap(),
         &cw_otc_common::msgs::ExecuteMsg::CreateOtc(CreateOtcMsg {
-            executor: Some(executor.to_string()),
+            executors: vec![executor.to_string()],
             offer: offer.to_vec(),
             ask: ask.to_vec(),
+            expires_at: None,
+            staged: None,
+            partial_fill: None,
         }),
         &coins,
     )
 }
 
+pub fn run_create_otc_msg(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    creator: &str,
+    msg: CreateOtcMsg,
+    coins: Vec<Coin>,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(creator),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::CreateOtc(msg),
+        &coins.merge(),
+    )
+}
+
+pub fn run_create_otc_staged(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    creator: &str,
+    executor: &str,
+    offer: &[OtcItemRegistration],
+    ask: &[OtcItemRegistration],
+    coins: Vec<Coin>,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(creator),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::CreateOtc(CreateOtcMsg {
+            executors: vec![executor.to_string()],
+            offer: offer.to_vec(),
+            ask: ask.to_vec(),
+            expires_at: None,
+            staged: Some(true),
+            partial_fill: None,
+        }),
+        &coins.merge(),
+    )
+}
+
+pub fn run_deposit_otc(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    items: Option<Vec<u32>>,
+    coins: Vec<Coin>,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::DepositOtc(DepositOtcMsg { id, items }),
+        &coins.merge(),
+    )
+}
+
+pub fn run_deposit_otc_cw20(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    addr: &Addr,
+    amount: u128,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        addr.clone(),
+        &cw20::Cw20ExecuteMsg::Send {
+            contract: def.addr_otc.clone().unwrap().to_string(),
+            amount: amount.into(),
+            msg: to_json_binary(&cw_otc_common::msgs::Cw20HookMsg::DepositOtc(
+                DepositOtcMsg { id, items: None },
+            ))
+            .unwrap(),
+        },
+        &[],
+    )
+}
+
+pub fn run_deposit_otc_nft(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    addr: &Addr,
+    token_id: &str,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        addr.clone(),
+        &cw721::Cw721ExecuteMsg::SendNft {
+            contract: def.addr_otc.clone().unwrap().to_string(),
+            token_id: token_id.to_string(),
+            msg: to_json_binary(&cw_otc_common::msgs::Cw721HookMsg::DepositOtc(
+                DepositOtcMsg { id, items: None },
+            ))
+            .unwrap(),
+        },
+        &[],
+    )
+}
+
+pub fn run_cancel_otc(app: &mut TestMockApp, def: &mut Def, sender: &str, id: u64) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::CancelOtc(CancelOtcMsg { id }),
+        &[],
+    )
+}
+
+pub fn run_claim_otc(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    recipient: Option<&str>,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::ClaimOtc(ClaimOtcMsg {
+            id,
+            recipient: recipient.map(|val| val.to_string()),
+        }),
+        &[],
+    )
+}
+
+pub fn run_set_payout_address(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    address: Option<&str>,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::SetPayoutAddress(SetPayoutAddressMsg {
+            id,
+            address: address.map(|val| val.to_string()),
+        }),
+        &[],
+    )
+}
+
+pub fn run_transfer_position_side(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    side: PositionSide,
+    new_address: &str,
+    require_acceptance: bool,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::TransferPositionSide(TransferPositionSideMsg {
+            id,
+            side,
+            new_address: new_address.to_string(),
+            require_acceptance: Some(require_acceptance),
+        }),
+        &[],
+    )
+}
+
+pub fn run_accept_position_side(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    side: PositionSide,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::AcceptPositionSide(AcceptPositionSideMsg { id, side }),
+        &[],
+    )
+}
+
+pub fn run_claim_many(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    ids: Option<Vec<u64>>,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::ClaimMany(ClaimManyMsg { ids, limit: None }),
+        &[],
+    )
+}
+
 pub fn run_execute_otc(
     app: &mut TestMockApp,
     def: &mut Def,
@@ -296,13 +559,155 @@ pub fn run_execute_otc(
     app.execute(
         Addr::unchecked(sender),
         def.addr_otc.clone().unwrap(),
-        &cw_otc_common::msgs::ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id }),
+        &cw_otc_common::msgs::ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id, fill: None }),
         &coins,
     )
 }
 
+pub fn run_fill_otc(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    fill: Decimal,
+    coins: Vec<Coin>,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::ExecuteOtc(ExecuteOtcMsg {
+            id,
+            fill: Some(fill),
+        }),
+        &coins.merge(),
+    )
+}
+
+pub fn run_update_otc(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    msg: UpdateOtcMsg,
+    coins: Vec<Coin>,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::UpdateOtc(msg),
+        &coins.merge(),
+    )
+}
+
+pub fn run_counter_offer(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    ask: &[OtcItemRegistration],
+    coins: Vec<Coin>,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::CounterOffer(CounterOfferMsg {
+            id,
+            ask: ask.to_vec(),
+        }),
+        &coins.merge(),
+    )
+}
+
+pub fn run_accept_counter_offer(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    proposer: &str,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::AcceptCounterOffer(CounterOfferActionMsg {
+            id,
+            proposer: proposer.to_string(),
+        }),
+        &[],
+    )
+}
+
+pub fn run_reject_counter_offer(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    id: u64,
+    proposer: &str,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::RejectCounterOffer(CounterOfferActionMsg {
+            id,
+            proposer: proposer.to_string(),
+        }),
+        &[],
+    )
+}
+
+pub fn run_update_config(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    msg: UpdateConfigMsg,
+) -> AppResult {
+    if let Some(fee) = &msg.fee {
+        def.otc_fee = fee.clone();
+    }
+
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::UpdateConfig(msg),
+        &[],
+    )
+}
+
+pub fn run_set_pause(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    msg: SetPauseMsg,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::SetPause(msg),
+        &[],
+    )
+}
+
+pub fn run_set_guardian(
+    app: &mut TestMockApp,
+    def: &mut Def,
+    sender: &str,
+    msg: SetGuardianMsg,
+) -> AppResult {
+    app.execute(
+        Addr::unchecked(sender),
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::ExecuteMsg::SetGuardian(msg),
+        &[],
+    )
+}
+
 // queries
 
+pub fn qy_otc_config(app: &TestMockApp, def: &Def) -> StdResult<Config> {
+    app.query(
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::QueryMsg::Config {},
+    )
+}
+
 pub fn qy_otc_active_position(app: &TestMockApp, def: &Def, id: u64) -> StdResult<OtcPosition> {
     app.query(
         def.addr_otc.clone().unwrap(),
@@ -317,6 +722,81 @@ pub fn qy_otc_executed_position(app: &TestMockApp, def: &Def, id: u64) -> StdRes
     )
 }
 
+pub fn qy_otc_positions_by_executor(
+    app: &TestMockApp,
+    def: &Def,
+    executor: &str,
+) -> StdResult<Vec<OtcPosition>> {
+    app.query(
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::QueryMsg::Positions {
+            start_after: None,
+            limit: None,
+            filters: Some(QueryPositionsFilter {
+                owner: None,
+                executor: Some(executor.to_string()),
+                status: None,
+            }),
+            order: None,
+        },
+    )
+}
+
+pub fn qy_otc_positions_by_owner(
+    app: &TestMockApp,
+    def: &Def,
+    owner: &str,
+) -> StdResult<Vec<OtcPosition>> {
+    app.query(
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::QueryMsg::Positions {
+            start_after: None,
+            limit: None,
+            filters: Some(QueryPositionsFilter {
+                owner: Some(owner.to_string()),
+                executor: None,
+                status: None,
+            }),
+            order: None,
+        },
+    )
+}
+
+pub fn qy_otc_counter_offers(
+    app: &TestMockApp,
+    def: &Def,
+    id: u64,
+    start_after: Option<&str>,
+) -> StdResult<Vec<CounterOffer>> {
+    app.query(
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::QueryMsg::CounterOffers {
+            id,
+            limit: None,
+            start_after: start_after.map(|val| val.to_string()),
+        },
+    )
+}
+
+pub fn qy_otc_claimable(
+    app: &TestMockApp,
+    def: &Def,
+    id: u64,
+    address: &str,
+    at_time: Option<u64>,
+    at_height: Option<u64>,
+) -> StdResult<Vec<ClaimableItem>> {
+    app.query(
+        def.addr_otc.clone().unwrap(),
+        &cw_otc_common::msgs::QueryMsg::Claimable {
+            id,
+            address: address.to_string(),
+            at_time,
+            at_height,
+        },
+    )
+}
+
 pub fn qy_balance_native(app: &TestMockApp, denom: &str, user: &str) -> Uint128 {
     app.query_balance(Addr::unchecked(user), denom.to_string())
         .unwrap()
@@ -1,13 +1,27 @@
-use cosmwasm_std::Coin;
+use std::str::FromStr;
+
+use cosmwasm_std::{Addr, Coin, Decimal};
 use cosmwasm_testing_util::test_tube::FEE_DENOM;
-use cw_otc_common::{definitions::OtcItemInfo, msgs::OtcItemRegistration};
+use cw_otc_common::{
+    definitions::{OtcItemInfo, OtcPositionStatus, PositionSide, VestingCheckpoint, VestingClock},
+    msgs::{
+        CreateOtcMsg, OtcItemRegistration, PartialFillRegistration, SetGuardianMsg, SetPauseMsg,
+        UpdateConfigMsg, UpdateOtcMsg, VestingInfoRegistration,
+    },
+};
 
 use crate::tests::app_ext::TestMockApp;
 
 use super::helper::{
-    create_token, increase_allowance, mint_token, qy_balance_cw20, qy_balance_native,
-    qy_balance_nft, qy_otc_active_position, qy_otc_executed_position, run_create_otc,
-    run_execute_otc, startup, Def, TokenType,
+    create_position_nft, create_token, increase_allowance, mint_token, qy_balance_cw20,
+    qy_balance_native, qy_balance_nft, qy_otc_active_position, qy_otc_claimable, qy_otc_config,
+    qy_otc_counter_offers, qy_otc_executed_position, qy_otc_positions_by_executor,
+    qy_otc_positions_by_owner, run_accept_counter_offer, run_accept_position_side, run_cancel_otc,
+    run_claim_many, run_claim_otc, run_counter_offer, run_create_otc, run_create_otc_msg,
+    run_create_otc_staged, run_deposit_otc, run_deposit_otc_cw20, run_deposit_otc_nft,
+    run_execute_otc, run_fill_otc, run_reject_counter_offer, run_set_guardian, run_set_pause,
+    run_set_payout_address, run_transfer_position_side, run_update_config, run_update_otc, startup,
+    Def, TokenType,
 };
 
 #[test]
@@ -29,6 +43,8 @@ pub fn test() {
     let executor = &accounts[3];
 
     let fee = def.get_native_fee();
+
+    assert_eq!(def.otc_fee, qy_otc_config(&app, &def).unwrap().fee);
    
     // Create tokens
 
@@ -107,3 +123,1056 @@ pub fn test() {
     qy_otc_active_position(&app, &def, 1).unwrap();
 
 }
+
+#[test]
+#[rustfmt::skip]
+pub fn test_cw20_fee() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor = &accounts[3];
+    let otc = def.addr_otc.clone().unwrap();
+
+    // Set a cw20 fee on the same token used in the offer
+
+    let fee_amount = 10_u128;
+    let offer_cw20_amount = 100_u128;
+    let ask_native_amount = 250_u128;
+
+    let cw20_addr = create_token(&mut app, &mut def, "TokenFee", TokenType::Cw20, vec![(creator, &(offer_cw20_amount * 2 + fee_amount).to_string()), (executor, &fee_amount.to_string())]);
+
+    run_update_config(&mut app, &mut def, creator, UpdateConfigMsg { fee: Some(vec![OtcItemInfo::Cw20 { contract: cw20_addr.clone(), amount: fee_amount.into() }]), fee_collector: None, strict_funds: None, trade_fee: None, position_nft: None }).unwrap_err();
+    run_update_config(&mut app, &mut def, def.owner, UpdateConfigMsg { fee: Some(vec![OtcItemInfo::Cw20 { contract: cw20_addr.clone(), amount: fee_amount.into() }]), fee_collector: None, strict_funds: None, trade_fee: None, position_nft: None }).unwrap();
+
+    let offer_items = vec![
+        OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: None },
+    ];
+
+    let ask_items = vec![
+        OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None },
+    ];
+
+    // First position, the fee is pulled from the creator
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &cw20_addr, TokenType::Cw20, &(offer_cw20_amount + fee_amount).to_string());
+    run_create_otc(&mut app, &mut def, creator, executor, &offer_items, &ask_items, vec![]).unwrap();
+
+    assert_eq!(offer_cw20_amount, qy_balance_cw20(&app, &cw20_addr, otc.as_ref()).u128());
+    assert_eq!(fee_amount, qy_balance_cw20(&app, &cw20_addr, def.fee_collector).u128());
+
+    // Without allowance for the fee the creation fails, the escrow is never used to pay it
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &cw20_addr, TokenType::Cw20, &offer_cw20_amount.to_string());
+    run_create_otc(&mut app, &mut def, creator, executor, &offer_items, &ask_items, vec![]).unwrap_err();
+
+    assert_eq!(offer_cw20_amount, qy_balance_cw20(&app, &cw20_addr, otc.as_ref()).u128());
+    assert_eq!(fee_amount, qy_balance_cw20(&app, &cw20_addr, def.fee_collector).u128());
+
+    // The executor pays the fee from its own balance
+
+    run_execute_otc(&mut app, &mut def, executor, 1, vec![]).unwrap_err();
+    increase_allowance(&mut app, executor, otc.as_ref(), &cw20_addr, TokenType::Cw20, &fee_amount.to_string());
+    run_execute_otc(&mut app, &mut def, executor, 1, vec![]).unwrap();
+
+    assert_eq!(0, qy_balance_cw20(&app, &cw20_addr, otc.as_ref()).u128());
+    assert_eq!(offer_cw20_amount, qy_balance_cw20(&app, &cw20_addr, executor).u128());
+    assert_eq!(fee_amount * 2, qy_balance_cw20(&app, &cw20_addr, def.fee_collector).u128());
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_staged_deposit() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor = &accounts[3];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_nft_id = "1";
+    let offer_cw20_amount = 100_u128;
+    let offer_native_amount = 150_u128;
+    let ask_native_amount = 250_u128;
+
+    let offer_nft_addr = create_token(&mut app, &mut def, "NftOffer", TokenType::Cw721, vec![(creator, offer_nft_id)]);
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &(offer_cw20_amount * 2).to_string())]);
+
+    let offer_items = vec![
+        OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: offer_native_amount.into() }, vesting: None },
+        OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: None },
+        OtcItemRegistration { item_info: OtcItemInfo::Cw721 { contract: offer_nft_addr.clone(), token_id: offer_nft_id.to_string() }, vesting: None }
+    ];
+
+    let ask_items = vec![
+        OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None },
+    ];
+
+    // Create with only the native offer
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(offer_native_amount, FEE_DENOM));
+    run_create_otc_staged(&mut app, &mut def, creator, executor, &offer_items, &ask_items, coins).unwrap();
+
+    assert_eq!(OtcPositionStatus::Funding, qy_otc_active_position(&app, &def, 1).unwrap().status);
+    run_execute_otc(&mut app, &mut def, executor, 1, fee.clone()).unwrap_err();
+
+    // Deposit cw20 through allowance, then nft through hook
+
+    run_deposit_otc(&mut app, &mut def, creator, 1, Some(vec![1]), vec![]).unwrap_err();
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &offer_cw20_amount.to_string());
+    run_deposit_otc(&mut app, &mut def, executor, 1, Some(vec![1]), vec![]).unwrap_err();
+    run_deposit_otc(&mut app, &mut def, creator, 1, Some(vec![1]), vec![]).unwrap();
+    run_deposit_otc(&mut app, &mut def, creator, 1, Some(vec![1]), vec![]).unwrap_err();
+
+    assert_eq!(OtcPositionStatus::Funding, qy_otc_active_position(&app, &def, 1).unwrap().status);
+
+    run_deposit_otc_nft(&mut app, &mut def, creator, 1, &offer_nft_addr, offer_nft_id).unwrap();
+
+    assert_eq!(OtcPositionStatus::Pending, qy_otc_active_position(&app, &def, 1).unwrap().status);
+    assert_eq!(offer_cw20_amount, qy_balance_cw20(&app, &offer_cw20_addr, otc.as_ref()).u128());
+    assert!(qy_balance_nft(&app, &offer_nft_addr, offer_nft_id, otc.as_ref()));
+
+    run_execute_otc(&mut app, &mut def, executor, 1, fee.clone()).unwrap();
+
+    assert_eq!(offer_cw20_amount, qy_balance_cw20(&app, &offer_cw20_addr, executor).u128());
+    assert!(qy_balance_nft(&app, &offer_nft_addr, offer_nft_id, executor));
+
+    // Partially funded position is refunded on cancel
+
+    run_create_otc_staged(&mut app, &mut def, creator, executor, &offer_items[1..2], &ask_items, fee.clone()).unwrap();
+    run_deposit_otc_cw20(&mut app, &mut def, creator, 2, &offer_cw20_addr, offer_cw20_amount).unwrap();
+
+    assert_eq!(0, qy_balance_cw20(&app, &offer_cw20_addr, creator).u128());
+
+    run_cancel_otc(&mut app, &mut def, executor, 2).unwrap_err();
+    run_cancel_otc(&mut app, &mut def, creator, 2).unwrap();
+
+    assert_eq!(offer_cw20_amount, qy_balance_cw20(&app, &offer_cw20_addr, creator).u128());
+    assert_eq!(0, qy_balance_cw20(&app, &offer_cw20_addr, otc.as_ref()).u128());
+
+    // Cancelled position is kept as history
+
+    let position = qy_otc_executed_position(&app, &def, 2).unwrap();
+    assert!(matches!(position.status, OtcPositionStatus::Cancelled(_)));
+
+    run_cancel_otc(&mut app, &mut def, creator, 2).unwrap_err();
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_partial_fill() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor_1",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor_2",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor_1 = &accounts[3];
+    let executor_2 = &accounts[4];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_cw20_amount = 1_001_u128;
+    let ask_native_amount = 500_u128;
+
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &offer_cw20_amount.to_string())]);
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &offer_cw20_amount.to_string());
+
+    let msg = CreateOtcMsg {
+        executors: vec![],
+        offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: None }],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: Some(PartialFillRegistration { min_fill: Decimal::from_str("0.25").unwrap() }),
+    };
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg, fee.clone()).unwrap();
+
+    // Fill lower than min fill
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(50, FEE_DENOM));
+    run_fill_otc(&mut app, &mut def, executor_1, 1, Decimal::from_str("0.1").unwrap(), coins).unwrap_err();
+
+    // First fill of 1/3
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(166, FEE_DENOM));
+    run_fill_otc(&mut app, &mut def, executor_1, 1, Decimal::from_str("0.333333333333333333").unwrap(), coins).unwrap();
+
+    assert_eq!(333, qy_balance_cw20(&app, &offer_cw20_addr, executor_1).u128());
+
+    let position = qy_otc_active_position(&app, &def, 1).unwrap();
+    assert_eq!(OtcPositionStatus::Pending, position.status);
+    assert_eq!(None, position.executor);
+
+    // A second executor fills the remaining, the last fill gets the exact remaining amounts
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount - 166, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor_2, 1, coins).unwrap();
+
+    assert_eq!(offer_cw20_amount - 333, qy_balance_cw20(&app, &offer_cw20_addr, executor_2).u128());
+    assert_eq!(0, qy_balance_cw20(&app, &offer_cw20_addr, otc.as_ref()).u128());
+
+    let position = qy_otc_active_position(&app, &def, 1).unwrap();
+    assert_eq!(Decimal::one(), position.partial_fill.unwrap().filled);
+    assert!(matches!(position.status, OtcPositionStatus::Executed(_)));
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_executors_allowlist() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor_1",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor_2",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("outsider",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor_1 = &accounts[3];
+    let executor_2 = &accounts[4];
+    let outsider = &accounts[5];
+
+    let fee = def.get_native_fee();
+
+    let offer_native_amount = 1_000_u128;
+    let ask_native_amount = 2_000_u128;
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor_1.to_string(), executor_2.to_string()],
+        offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: offer_native_amount.into() }, vesting: None }],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(offer_native_amount, FEE_DENOM));
+    run_create_otc_msg(&mut app, &mut def, creator, msg, coins).unwrap();
+
+    // Every allowed executor finds the position
+
+    assert_eq!(1, qy_otc_positions_by_executor(&app, &def, executor_1).unwrap().len());
+    assert_eq!(1, qy_otc_positions_by_executor(&app, &def, executor_2).unwrap().len());
+    assert_eq!(0, qy_otc_positions_by_executor(&app, &def, outsider).unwrap().len());
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+
+    run_execute_otc(&mut app, &mut def, outsider, 1, coins.clone()).unwrap_err();
+    run_execute_otc(&mut app, &mut def, executor_2, 1, coins).unwrap();
+
+    // Once executed the position is indexed only by its executor
+
+    assert_eq!(0, qy_otc_positions_by_executor(&app, &def, executor_1).unwrap().len());
+    assert_eq!(Some(executor_2.to_string()), qy_otc_positions_by_executor(&app, &def, executor_2).unwrap()[0].executor.as_ref().map(|val| val.to_string()));
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_update_otc() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor_1",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor_2",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor_1 = &accounts[3];
+    let executor_2 = &accounts[4];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_cw20_amount = 1_000_u128;
+    let ask_native_amount = 2_000_u128;
+
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &(offer_cw20_amount * 2).to_string())]);
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &(offer_cw20_amount * 2).to_string());
+
+    let offer = |amount: u128| vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: amount.into() }, vesting: None }];
+    let ask = |amount: u128| vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: amount.into() }, vesting: None }];
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor_1.to_string()],
+        offer: offer(offer_cw20_amount),
+        ask: ask(ask_native_amount),
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg, fee.clone()).unwrap();
+
+    let update = |offer: Option<Vec<OtcItemRegistration>>| UpdateOtcMsg {
+        id: 1,
+        executors: Some(vec![executor_2.to_string()]),
+        offer,
+        ask: Some(ask(ask_native_amount / 2)),
+        expires_at: None,
+    };
+
+    // Only the owner can update
+
+    run_update_otc(&mut app, &mut def, executor_1, update(None), vec![]).unwrap_err();
+
+    // Top up the offer, the difference is pulled
+
+    run_update_otc(&mut app, &mut def, creator, update(Some(offer(offer_cw20_amount * 2))), vec![]).unwrap();
+    assert_eq!(offer_cw20_amount * 2, qy_balance_cw20(&app, &offer_cw20_addr, otc.as_ref()).u128());
+
+    // Reduce the offer, the difference is refunded
+
+    run_update_otc(&mut app, &mut def, creator, update(Some(offer(offer_cw20_amount / 2))), vec![]).unwrap();
+    assert_eq!(offer_cw20_amount / 2, qy_balance_cw20(&app, &offer_cw20_addr, otc.as_ref()).u128());
+    assert_eq!(offer_cw20_amount * 3 / 2, qy_balance_cw20(&app, &offer_cw20_addr, creator).u128());
+
+    let position = qy_otc_active_position(&app, &def, 1).unwrap();
+    assert_eq!(Some(executor_2.to_string()), position.executor.map(|val| val.to_string()));
+    assert_eq!(1, position.id);
+
+    // The old executor lost the rights, the new one executes with the updated ask
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount / 2, FEE_DENOM));
+
+    run_execute_otc(&mut app, &mut def, executor_1, 1, coins.clone()).unwrap_err();
+    run_execute_otc(&mut app, &mut def, executor_2, 1, coins).unwrap();
+
+    assert_eq!(offer_cw20_amount / 2, qy_balance_cw20(&app, &offer_cw20_addr, executor_2).u128());
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_counter_offer() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor_1",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor_2",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor_1 = &accounts[3];
+    let executor_2 = &accounts[4];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_cw20_amount = 1_000_u128;
+    let ask_cw20_amount = 2_000_u128;
+
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &offer_cw20_amount.to_string())]);
+    let ask_cw20_addr = create_token(&mut app, &mut def, "TokenAsk", TokenType::Cw20, vec![(executor_1, &ask_cw20_amount.to_string()), (executor_2, &ask_cw20_amount.to_string())]);
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &offer_cw20_amount.to_string());
+    increase_allowance(&mut app, executor_1, otc.as_ref(), &ask_cw20_addr, TokenType::Cw20, &ask_cw20_amount.to_string());
+    increase_allowance(&mut app, executor_2, otc.as_ref(), &ask_cw20_addr, TokenType::Cw20, &ask_cw20_amount.to_string());
+
+    let offer_items = vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: None }];
+    let ask = |amount: u128| vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: ask_cw20_addr.clone(), amount: amount.into() }, vesting: None }];
+
+    let msg = CreateOtcMsg {
+        executors: vec![],
+        offer: offer_items,
+        ask: ask(ask_cw20_amount),
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg, fee.clone()).unwrap();
+
+    // Counter offers are escrowed
+
+    run_counter_offer(&mut app, &mut def, creator, 1, &ask(ask_cw20_amount / 2), fee.clone()).unwrap_err();
+    run_counter_offer(&mut app, &mut def, executor_1, 1, &ask(ask_cw20_amount / 2), fee.clone()).unwrap();
+    run_counter_offer(&mut app, &mut def, executor_1, 1, &ask(ask_cw20_amount / 2), fee.clone()).unwrap_err();
+    run_counter_offer(&mut app, &mut def, executor_2, 1, &ask(ask_cw20_amount * 3 / 4), fee.clone()).unwrap();
+
+    assert_eq!(ask_cw20_amount * 5 / 4, qy_balance_cw20(&app, &ask_cw20_addr, otc.as_ref()).u128());
+    assert_eq!(2, qy_otc_counter_offers(&app, &def, 1, None).unwrap().len());
+    assert_eq!(1, qy_otc_counter_offers(&app, &def, 1, Some(&qy_otc_counter_offers(&app, &def, 1, None).unwrap()[0].proposer.to_string())).unwrap().len());
+
+    // Rejected counter offer is refunded
+
+    run_reject_counter_offer(&mut app, &mut def, executor_2, 1, executor_1).unwrap_err();
+    run_reject_counter_offer(&mut app, &mut def, creator, 1, executor_1).unwrap();
+
+    assert_eq!(ask_cw20_amount, qy_balance_cw20(&app, &ask_cw20_addr, executor_1).u128());
+
+    // Accepted counter offer executes the position
+
+    run_accept_counter_offer(&mut app, &mut def, executor_2, 1, executor_2).unwrap_err();
+    run_accept_counter_offer(&mut app, &mut def, creator, 1, executor_2).unwrap();
+
+    assert_eq!(ask_cw20_amount * 3 / 4, qy_balance_cw20(&app, &ask_cw20_addr, creator).u128());
+    assert_eq!(offer_cw20_amount, qy_balance_cw20(&app, &offer_cw20_addr, executor_2).u128());
+    assert_eq!(0, qy_otc_counter_offers(&app, &def, 1, None).unwrap().len());
+
+    let position = qy_otc_executed_position(&app, &def, 1).unwrap();
+    assert!(matches!(position.status, OtcPositionStatus::Executed(_)));
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_claimable() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor = &accounts[3];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_cw20_amount = 1_000_u128;
+    let ask_native_amount = 2_000_u128;
+
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &offer_cw20_amount.to_string())]);
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &offer_cw20_amount.to_string());
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor.to_string()],
+        offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: Some(VestingInfoRegistration::Linear { cliff: Some(100), vesting: Some(1_000), clock: None, start_at: None }) }],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg, fee.clone()).unwrap();
+
+    // Nothing vested before the execution
+
+    let claimable = qy_otc_claimable(&app, &def, 1, executor, None, None).unwrap();
+    assert_eq!(0, claimable[0].vested.u128());
+    assert_eq!(None, claimable[0].next_unlock);
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 1, coins).unwrap();
+
+    let vesting_start = qy_otc_active_position(&app, &def, 1).unwrap().status.get_vesting_start().unwrap().time;
+
+    // Cliff
+
+    let claimable = qy_otc_claimable(&app, &def, 1, executor, Some(vesting_start + 50), None).unwrap();
+    assert_eq!(0, claimable[0].claimable.u128());
+    assert_eq!(Some(vesting_start + 101), claimable[0].next_unlock);
+
+    // Half vested, nothing claimed yet
+
+    let claimable = qy_otc_claimable(&app, &def, 1, executor, Some(vesting_start + 600), None).unwrap();
+    assert_eq!(offer_cw20_amount / 2, claimable[0].vested.u128());
+    assert_eq!(0, claimable[0].claimed.u128());
+    assert_eq!(offer_cw20_amount / 2, claimable[0].claimable.u128());
+
+    // Fully vested
+
+    let claimable = qy_otc_claimable(&app, &def, 1, executor, Some(vesting_start + 2_000), None).unwrap();
+    assert_eq!(offer_cw20_amount, claimable[0].claimable.u128());
+    assert_eq!(None, claimable[0].next_unlock);
+
+    // The ask without vesting has been released on execution
+
+    let claimable = qy_otc_claimable(&app, &def, 1, creator, None, None).unwrap();
+    assert_eq!(0, claimable[0].claimable.u128());
+    assert_eq!(ask_native_amount, claimable[0].claimed.u128());
+
+    qy_otc_claimable(&app, &def, 1, &accounts[0], None, None).unwrap_err();
+
+    // Periodic and custom schedules
+
+    let offer_native_amount = 1_001_u128;
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor.to_string()],
+        offer: vec![
+            OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: offer_native_amount.into() }, vesting: Some(VestingInfoRegistration::Periodic { cliff: None, period: 100, steps: 3, clock: None, start_at: None }) },
+            OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: offer_native_amount.into() }, vesting: Some(VestingInfoRegistration::Custom { checkpoints: vec![
+                VestingCheckpoint { time: 10, percentage: Decimal::from_str("0.1").unwrap() },
+                VestingCheckpoint { time: 500, percentage: Decimal::one() },
+            ], clock: None, start_at: None }) },
+        ],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(offer_native_amount * 2, FEE_DENOM));
+    run_create_otc_msg(&mut app, &mut def, creator, msg, coins).unwrap();
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 2, coins).unwrap();
+
+    let vesting_start = qy_otc_active_position(&app, &def, 2).unwrap().status.get_vesting_start().unwrap().time;
+
+    let claimable = qy_otc_claimable(&app, &def, 2, executor, Some(vesting_start + 150), None).unwrap();
+    assert_eq!(333, claimable[0].vested.u128());
+    assert_eq!(Some(vesting_start + 200), claimable[0].next_unlock);
+    assert_eq!(100, claimable[1].vested.u128());
+    assert_eq!(Some(vesting_start + 500), claimable[1].next_unlock);
+
+    let claimable = qy_otc_claimable(&app, &def, 2, executor, Some(vesting_start + 500), None).unwrap();
+    assert_eq!(offer_native_amount, claimable[0].vested.u128());
+    assert_eq!(offer_native_amount, claimable[1].vested.u128());
+    assert_eq!(None, claimable[1].next_unlock);
+
+    // Vesting anchored before the execution, the amount already vested is released on execution
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor.to_string()],
+        offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: offer_native_amount.into() }, vesting: None }],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(offer_native_amount, FEE_DENOM));
+    run_create_otc_msg(&mut app, &mut def, creator, msg, coins).unwrap();
+
+    let start_at = qy_otc_active_position(&app, &def, 3).unwrap().creation_time - 500;
+
+    let update = UpdateOtcMsg {
+        id: 3,
+        executors: None,
+        offer: Some(vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: offer_native_amount.into() }, vesting: Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: Some(start_at) }) }]),
+        ask: None,
+        expires_at: None,
+    };
+
+    run_update_otc(&mut app, &mut def, creator, update, vec![]).unwrap();
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 3, coins).unwrap();
+
+    let claimable = qy_otc_claimable(&app, &def, 3, executor, None, None).unwrap();
+    assert!(claimable[0].claimed.u128() >= offer_native_amount / 2);
+
+    let claimable = qy_otc_claimable(&app, &def, 3, executor, Some(start_at + 1_000), None).unwrap();
+    assert_eq!(offer_native_amount, claimable[0].vested.u128());
+
+    // Height based vesting ignores the block time
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor.to_string()],
+        offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: offer_native_amount.into() }, vesting: Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(100), clock: Some(VestingClock::Height), start_at: None }) }],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(offer_native_amount, FEE_DENOM));
+    run_create_otc_msg(&mut app, &mut def, creator, msg, coins).unwrap();
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 4, coins).unwrap();
+
+    let vesting_start = qy_otc_active_position(&app, &def, 4).unwrap().status.get_vesting_start().unwrap();
+
+    let claimable = qy_otc_claimable(&app, &def, 4, executor, Some(vesting_start.time + 1_000_000), Some(vesting_start.height + 50)).unwrap();
+    assert_eq!(500, claimable[0].vested.u128());
+    assert_eq!(Some(vesting_start.height + 51), claimable[0].next_unlock);
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_claim_many() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor = &accounts[3];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_cw20_amount = 1_000_000_u128;
+    let ask_native_amount = 2_000_u128;
+
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &(offer_cw20_amount * 2).to_string())]);
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &(offer_cw20_amount * 2).to_string());
+
+    for id in 1..=2 {
+        let msg = CreateOtcMsg {
+            executors: vec![executor.to_string()],
+            offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: None }) }],
+            ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+            expires_at: None,
+            staged: None,
+            partial_fill: None,
+        };
+
+        run_create_otc_msg(&mut app, &mut def, creator, msg, fee.clone()).unwrap();
+
+        let mut coins = fee.clone();
+        coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+        run_execute_otc(&mut app, &mut def, executor, id, coins).unwrap();
+    }
+
+    // Only the owner and the executor can claim
+
+    run_claim_many(&mut app, &mut def, &accounts[0], Some(vec![1, 2])).unwrap_err();
+
+    // The ask has been released on execution
+
+    run_claim_many(&mut app, &mut def, creator, None).unwrap_err();
+
+    // Both vesting positions of the executor are claimed at once
+
+    run_claim_many(&mut app, &mut def, executor, None).unwrap();
+
+    let claimed = qy_balance_cw20(&app, &offer_cw20_addr, executor) + qy_balance_cw20(&app, &offer_cw20_addr, def.fee_collector);
+    assert!(claimed.u128() > 0);
+    assert_eq!(offer_cw20_amount * 2, claimed.u128() + qy_balance_cw20(&app, &offer_cw20_addr, otc.as_str()).u128());
+
+    for id in 1..=2 {
+        let position = qy_otc_active_position(&app, &def, id).unwrap();
+        assert!(position.offer[0].vesting_info.as_ref().unwrap().claimed.u128() > 0);
+    }
+
+    // Repeated ids are claimed once
+
+    run_claim_many(&mut app, &mut def, executor, Some(vec![1, 1])).unwrap();
+
+    let claimed = qy_balance_cw20(&app, &offer_cw20_addr, executor) + qy_balance_cw20(&app, &offer_cw20_addr, def.fee_collector);
+    assert_eq!(offer_cw20_amount * 2, claimed.u128() + qy_balance_cw20(&app, &offer_cw20_addr, otc.as_str()).u128());
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_payout_address() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("treasury",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("cold_wallet",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor = &accounts[3];
+    let treasury = &accounts[4];
+    let cold_wallet = &accounts[5];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_cw20_amount = 1_000_000_u128;
+    let ask_native_amount = 2_000_u128;
+
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &(offer_cw20_amount * 2).to_string())]);
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &(offer_cw20_amount * 2).to_string());
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor.to_string()],
+        offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: None }],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg.clone(), fee.clone()).unwrap();
+
+    // Only the owner and the executor can set their payout address
+
+    run_set_payout_address(&mut app, &mut def, treasury, 1, Some(treasury)).unwrap_err();
+    run_set_payout_address(&mut app, &mut def, creator, 1, Some(treasury)).unwrap();
+    run_set_payout_address(&mut app, &mut def, executor, 1, Some(cold_wallet)).unwrap();
+
+    let position = qy_otc_active_position(&app, &def, 1).unwrap();
+    assert_eq!(Some(treasury.to_string()), position.owner_payout.map(|val| val.to_string()));
+    assert_eq!(Some(cold_wallet.to_string()), position.executor_payout.map(|val| val.to_string()));
+
+    // Items released on execution go to the payout addresses
+
+    let treasury_balance = qy_balance_native(&app, FEE_DENOM, treasury);
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 1, coins).unwrap();
+
+    assert_eq!(treasury_balance.u128() + ask_native_amount, qy_balance_native(&app, FEE_DENOM, treasury).u128());
+    assert_eq!(offer_cw20_amount, qy_balance_cw20(&app, &offer_cw20_addr, cold_wallet).u128());
+    assert_eq!(0, qy_balance_cw20(&app, &offer_cw20_addr, executor).u128());
+
+    // Closed positions can't be updated
+
+    run_set_payout_address(&mut app, &mut def, creator, 1, None).unwrap_err();
+
+    // Vested items are claimed to the recipient, else to the payout address
+
+    let mut msg = msg;
+    msg.offer[0].vesting = Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: None });
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg, fee.clone()).unwrap();
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 2, coins).unwrap();
+
+    run_claim_otc(&mut app, &mut def, executor, 2, Some(treasury)).unwrap();
+
+    assert!(qy_balance_cw20(&app, &offer_cw20_addr, treasury).u128() > 0);
+    assert_eq!(offer_cw20_amount, qy_balance_cw20(&app, &offer_cw20_addr, cold_wallet).u128());
+
+    run_set_payout_address(&mut app, &mut def, executor, 2, Some(cold_wallet)).unwrap();
+    run_claim_otc(&mut app, &mut def, executor, 2, None).unwrap();
+
+    assert!(qy_balance_cw20(&app, &offer_cw20_addr, cold_wallet).u128() > offer_cw20_amount);
+    assert_eq!(0, qy_balance_cw20(&app, &offer_cw20_addr, executor).u128());
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_position_nft() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("buyer",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor = &accounts[3];
+    let buyer = &accounts[4];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let position_nft = create_position_nft(&mut app, &mut def);
+
+    run_update_config(&mut app, &mut def, def.owner, UpdateConfigMsg { fee: None, fee_collector: None, strict_funds: None, trade_fee: None, position_nft: Some(position_nft.to_string()) }).unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_cw20_amount = 1_000_000_u128;
+    let ask_native_amount = 2_000_u128;
+
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &offer_cw20_amount.to_string())]);
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &offer_cw20_amount.to_string());
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor.to_string()],
+        offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: None }) }],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg, fee.clone()).unwrap();
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 1, coins).unwrap();
+
+    // A token is minted for each side when the position enters vesting
+
+    let position = qy_otc_active_position(&app, &def, 1).unwrap();
+    assert_eq!(Some(position_nft.clone()), position.position_nft);
+    assert!(qy_balance_nft(&app, &position_nft, "1-owner", creator));
+    assert!(qy_balance_nft(&app, &position_nft, "1-executor", executor));
+
+    // The executor side is sold, only the holder can claim
+
+    app.execute(Addr::unchecked(executor), position_nft.clone(), &cw721::Cw721ExecuteMsg::TransferNft { recipient: buyer.to_string(), token_id: "1-executor".to_string() }, &[]).unwrap();
+
+    run_claim_otc(&mut app, &mut def, executor, 1, None).unwrap_err();
+    qy_otc_claimable(&app, &def, 1, executor, None, None).unwrap_err();
+
+    run_claim_otc(&mut app, &mut def, buyer, 1, None).unwrap();
+    assert!(qy_balance_cw20(&app, &offer_cw20_addr, buyer).u128() > 0);
+    assert_eq!(0, qy_balance_cw20(&app, &offer_cw20_addr, executor).u128());
+
+    qy_otc_claimable(&app, &def, 1, buyer, None, None).unwrap();
+
+    // Payout addresses don't apply to tokenized positions
+
+    run_set_payout_address(&mut app, &mut def, creator, 1, Some(buyer)).unwrap_err();
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_transfer_position_side() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("new_owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("new_executor",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor = &accounts[3];
+    let new_owner = &accounts[4];
+    let new_executor = &accounts[5];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_cw20_amount = 1_000_000_u128;
+    let ask_native_amount = 2_000_u128;
+
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &offer_cw20_amount.to_string())]);
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &offer_cw20_amount.to_string());
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor.to_string()],
+        offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: None }) }],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg, fee.clone()).unwrap();
+
+    // The executor side can't be transferred before the execution
+
+    run_transfer_position_side(&mut app, &mut def, executor, 1, PositionSide::Executor, new_executor, false).unwrap_err();
+
+    // Only the current holder can transfer a side
+
+    run_transfer_position_side(&mut app, &mut def, new_owner, 1, PositionSide::Owner, new_owner, false).unwrap_err();
+
+    // Owner side with acceptance
+
+    run_transfer_position_side(&mut app, &mut def, creator, 1, PositionSide::Owner, new_owner, true).unwrap();
+
+    let position = qy_otc_active_position(&app, &def, 1).unwrap();
+    assert_eq!(creator.to_string(), position.owner.to_string());
+    assert_eq!(Some(new_owner.to_string()), position.pending_owner.map(|val| val.to_string()));
+
+    run_accept_position_side(&mut app, &mut def, executor, 1, PositionSide::Owner).unwrap_err();
+    run_accept_position_side(&mut app, &mut def, new_owner, 1, PositionSide::Owner).unwrap();
+
+    let position = qy_otc_active_position(&app, &def, 1).unwrap();
+    assert_eq!(new_owner.to_string(), position.owner.to_string());
+    assert_eq!(None, position.pending_owner);
+
+    assert_eq!(0, qy_otc_positions_by_owner(&app, &def, creator).unwrap().len());
+    assert_eq!(1, qy_otc_positions_by_owner(&app, &def, new_owner).unwrap().len());
+
+    // Executor side without acceptance
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 1, coins).unwrap();
+
+    run_transfer_position_side(&mut app, &mut def, executor, 1, PositionSide::Executor, new_executor, false).unwrap();
+
+    assert_eq!(0, qy_otc_positions_by_executor(&app, &def, executor).unwrap().len());
+    assert_eq!(1, qy_otc_positions_by_executor(&app, &def, new_executor).unwrap().len());
+
+    run_claim_otc(&mut app, &mut def, executor, 1, None).unwrap_err();
+    run_claim_otc(&mut app, &mut def, new_executor, 1, None).unwrap();
+
+    assert!(qy_balance_cw20(&app, &offer_cw20_addr, new_executor).u128() > 0);
+    assert_eq!(0, qy_balance_cw20(&app, &offer_cw20_addr, executor).u128());
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_pause() {
+
+    let (mut app, accounts) = TestMockApp::new(&[
+        ("owner",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("fee_collector",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("creator",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("executor",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+        ("guardian",&[Coin::new(100_000_000_000u128, FEE_DENOM)]),
+    ]);
+
+    let mut def = Def::new(&accounts[0],&accounts[1]);
+
+    startup(&mut app,&mut def);
+
+    let creator = &accounts[2];
+    let executor = &accounts[3];
+    let guardian = &accounts[4];
+    let otc = def.addr_otc.clone().unwrap();
+
+    let fee = def.get_native_fee();
+
+    let offer_cw20_amount = 1_000_000_u128;
+    let ask_native_amount = 2_000_u128;
+
+    let offer_cw20_addr = create_token(&mut app, &mut def, "TokenOffer", TokenType::Cw20, vec![(creator, &(offer_cw20_amount * 3).to_string())]);
+
+    increase_allowance(&mut app, creator, otc.as_ref(), &offer_cw20_addr, TokenType::Cw20, &(offer_cw20_amount * 3).to_string());
+
+    let msg = CreateOtcMsg {
+        executors: vec![executor.to_string()],
+        offer: vec![OtcItemRegistration { item_info: OtcItemInfo::Cw20 { contract: offer_cw20_addr.clone(), amount: offer_cw20_amount.into() }, vesting: Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: None }) }],
+        ask: vec![OtcItemRegistration { item_info: OtcItemInfo::Token { denom: FEE_DENOM.to_string(), amount: ask_native_amount.into() }, vesting: None }],
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    };
+
+    let no_pause = SetPauseMsg { create: None, execute: None, claim: None, cancel: None };
+
+    // Position 1 vesting, position 2 pending
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg.clone(), fee.clone()).unwrap();
+    run_create_otc_msg(&mut app, &mut def, creator, msg.clone(), fee.clone()).unwrap();
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 1, coins).unwrap();
+
+    // Only the owner and the guardian can pause
+
+    run_set_pause(&mut app, &mut def, guardian, SetPauseMsg { create: Some(true), ..no_pause.clone() }).unwrap_err();
+    run_set_guardian(&mut app, &mut def, creator, SetGuardianMsg { guardian: Some(guardian.to_string()) }).unwrap_err();
+    run_set_guardian(&mut app, &mut def, def.owner, SetGuardianMsg { guardian: Some(guardian.to_string()) }).unwrap();
+
+    run_set_pause(&mut app, &mut def, creator, SetPauseMsg { create: Some(true), ..no_pause.clone() }).unwrap_err();
+    run_set_pause(&mut app, &mut def, guardian, SetPauseMsg { create: Some(true), execute: Some(true), claim: Some(true), ..no_pause.clone() }).unwrap();
+
+    let config = qy_otc_config(&app, &def).unwrap();
+    assert!(config.pause.create && config.pause.execute && config.pause.claim);
+    assert!(!config.pause.cancel);
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg.clone(), fee.clone()).unwrap_err();
+
+    let mut coins = fee.clone();
+    coins.push(Coin::new(ask_native_amount, FEE_DENOM));
+    run_execute_otc(&mut app, &mut def, executor, 2, coins).unwrap_err();
+
+    run_claim_otc(&mut app, &mut def, executor, 1, None).unwrap_err();
+
+    // Cancel is not paused
+
+    run_cancel_otc(&mut app, &mut def, creator, 2).unwrap();
+
+    // The guardian can't unpause
+
+    run_set_pause(&mut app, &mut def, guardian, SetPauseMsg { claim: Some(false), ..no_pause.clone() }).unwrap_err();
+    run_set_pause(&mut app, &mut def, def.owner, SetPauseMsg { claim: Some(false), ..no_pause.clone() }).unwrap();
+
+    run_claim_otc(&mut app, &mut def, executor, 1, None).unwrap();
+    assert!(qy_balance_cw20(&app, &offer_cw20_addr, executor).u128() > 0);
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg.clone(), fee.clone()).unwrap_err();
+
+    // The owner can pause cancel too
+
+    run_set_pause(&mut app, &mut def, def.owner, SetPauseMsg { create: Some(false), cancel: Some(true), ..no_pause }).unwrap();
+
+    run_create_otc_msg(&mut app, &mut def, creator, msg, fee).unwrap();
+    run_cancel_otc(&mut app, &mut def, creator, 3).unwrap_err();
+
+    // Once removed, the guardian can't pause anymore
+
+    run_set_guardian(&mut app, &mut def, def.owner, SetGuardianMsg { guardian: None }).unwrap();
+    assert_eq!(qy_otc_config(&app, &def).unwrap().guardian, None);
+    run_set_pause(&mut app, &mut def, guardian, SetPauseMsg { create: Some(true), execute: None, claim: None, cancel: None }).unwrap_err();
+}
@@ -0,0 +1,137 @@
+use cosmwasm_std::{
+    testing::{mock_dependencies, mock_env},
+    Addr, Uint128,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw_otc_common::{
+    definitions::{
+        OtcItemInfo, OtcPositionStatus, TradeFee, VestingClock, VestingSchedule, VestingStart,
+    },
+    error::ContractError,
+    msgs::MigrateMsg,
+};
+
+use crate::{
+    contract::{migrate, CONTRACT_NAME, CONTRACT_VERSION},
+    migrations::v0_1,
+    state::{positions, CONFIG},
+};
+
+fn token(amount: u128) -> OtcItemInfo {
+    OtcItemInfo::Token {
+        denom: "uatom".to_string(),
+        amount: Uint128::new(amount),
+    }
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_migrate_from_v0_1() {
+    let mut deps = mock_dependencies();
+
+    let owner = Addr::unchecked("owner");
+    let executor = Addr::unchecked("executor");
+
+    v0_1::CONFIG.save(deps.as_mut().storage, &v0_1::Config {
+        owner: owner.clone(),
+        counter_otc: 3,
+        fee: vec![token(10)],
+        fee_collector: Addr::unchecked("fee_collector"),
+    }).unwrap();
+
+    let vesting = |claimed: u128| Some(v0_1::VestingInfo { cliff: Some(10), vesting: Some(100), claimed: Uint128::new(claimed) });
+
+    // Pending, with a single allowed executor
+    v0_1::POSITIONS.save(deps.as_mut().storage, 1, &v0_1::OtcPosition {
+        id: 1,
+        owner: owner.clone(),
+        executor: Some(executor.clone()),
+        offer: vec![v0_1::OtcItem { item_info: token(100), vesting_info: vesting(0) }],
+        ask: vec![v0_1::OtcItem { item_info: token(200), vesting_info: None }],
+        creation_time: 1,
+        status: v0_1::OtcPositionStatus::Pending,
+    }).unwrap();
+
+    // Vesting, the offer is partially claimed and the ask fully claimed
+    v0_1::POSITIONS.save(deps.as_mut().storage, 2, &v0_1::OtcPosition {
+        id: 2,
+        owner: owner.clone(),
+        executor: Some(executor.clone()),
+        offer: vec![
+            v0_1::OtcItem { item_info: token(100), vesting_info: vesting(40) },
+            v0_1::OtcItem { item_info: token(50), vesting_info: None },
+        ],
+        ask: vec![v0_1::OtcItem { item_info: token(200), vesting_info: vesting(200) }],
+        creation_time: 2,
+        status: v0_1::OtcPositionStatus::Vesting(20),
+    }).unwrap();
+
+    // Executed
+    v0_1::POSITIONS.save(deps.as_mut().storage, 3, &v0_1::OtcPosition {
+        id: 3,
+        owner: owner.clone(),
+        executor: Some(executor.clone()),
+        offer: vec![v0_1::OtcItem { item_info: token(100), vesting_info: None }],
+        ask: vec![v0_1::OtcItem { item_info: token(200), vesting_info: None }],
+        creation_time: 3,
+        status: v0_1::OtcPositionStatus::Executed(30),
+    }).unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert!(res.attributes.iter().any(|val| val.key == "from_version" && val.value == "0.1.0"));
+
+    let version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(version.contract, CONTRACT_NAME);
+    assert_eq!(version.version, CONTRACT_VERSION);
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(config.owner, owner);
+    assert_eq!(config.counter_otc, 3);
+    assert_eq!(config.fee, vec![token(10)]);
+    assert_eq!(config.pending_owner, None);
+    assert!(!config.strict_funds);
+    assert_eq!(config.trade_fee, TradeFee::default());
+    assert_eq!(config.position_nft, None);
+
+    let position = positions().load(deps.as_ref().storage, 1).unwrap();
+    assert_eq!(position.status, OtcPositionStatus::Pending);
+    assert_eq!(position.executor, Some(executor.clone()));
+    assert_eq!(position.executors, vec![executor.clone()]);
+    assert!(position.offer[0].deposited);
+    assert!(!position.ask[0].deposited);
+    let vesting_info = position.offer[0].vesting_info.clone().unwrap();
+    assert_eq!(vesting_info.schedule, VestingSchedule::Linear { cliff: Some(10), vesting: Some(100) });
+    assert_eq!(vesting_info.clock, VestingClock::Time);
+    assert_eq!(vesting_info.start_at, None);
+
+    let position = positions().load(deps.as_ref().storage, 2).unwrap();
+    assert_eq!(position.status, OtcPositionStatus::Vesting(VestingStart { time: 20, height: 0 }));
+    assert!(position.offer[0].deposited);
+    assert!(!position.offer[1].deposited);
+    assert!(!position.ask[0].deposited);
+    assert_eq!(position.offer[0].vesting_info.clone().unwrap().claimed, Uint128::new(40));
+    assert_eq!(position.trade_fee, TradeFee::default());
+
+    let position = positions().load(deps.as_ref().storage, 3).unwrap();
+    assert_eq!(position.status, OtcPositionStatus::Executed(30));
+    assert!(!position.offer[0].deposited);
+    assert!(!position.ask[0].deposited);
+
+    // Migrating again to the same version is a no-op
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert!(!positions().load(deps.as_ref().storage, 2).unwrap().offer[1].deposited);
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_migrate_refused() {
+    let mut deps = mock_dependencies();
+
+    set_contract_version(deps.as_mut().storage, "other-contract", CONTRACT_VERSION).unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(err, ContractError::InvalidContractName { expected: CONTRACT_NAME.to_string(), received: "other-contract".to_string() });
+
+    set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "99.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(err, ContractError::CannotDowngrade { from: "99.0.0".to_string(), to: CONTRACT_VERSION.to_string() });
+}
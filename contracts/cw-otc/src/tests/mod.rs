@@ -1,3 +1,6 @@
 mod app_ext;
 mod helper;
 mod integration_test;
+mod migrate_test;
+mod unit_helper;
+mod unit_test;
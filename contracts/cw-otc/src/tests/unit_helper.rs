@@ -0,0 +1,228 @@
+use cosmwasm_std::{
+    from_json,
+    testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage},
+    to_json_binary, Addr, BankMsg, Coin, ContractResult, CosmosMsg, Env, OwnedDeps, Response,
+    SystemError, SystemResult, WasmMsg, WasmQuery,
+};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw721::{Cw721ExecuteMsg, Cw721QueryMsg, Cw721ReceiveMsg, OwnerOfResponse};
+use cw_otc_common::{
+    definitions::{Config, OtcItemInfo, OtcPosition, TradeFee},
+    msgs::{Cw20HookMsg, Cw721HookMsg, ExecuteMsg, InstantiateMsg, OtcItemRegistration},
+};
+
+use crate::{
+    contract::{execute, instantiate},
+    response::ContractResponse,
+    state::{positions, CONFIG},
+};
+
+pub const OWNER: &str = "owner";
+pub const FEE_COLLECTOR: &str = "fee_collector";
+pub const FEE_DENOM: &str = "uotc";
+pub const FEE_AMOUNT: u128 = 100;
+
+pub type MockDeps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+/// Instantiate the contract on mock dependencies, with a native fee of `FEE_AMOUNT` `FEE_DENOM`
+pub fn mock_startup(strict_funds: bool, trade_fee: Option<TradeFee>) -> MockDeps {
+    let mut deps = mock_dependencies();
+
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(OWNER, &[]),
+        InstantiateMsg {
+            owner: OWNER.to_string(),
+            fee: vec![native(FEE_DENOM, FEE_AMOUNT)],
+            fee_collector: FEE_COLLECTOR.to_string(),
+            strict_funds: Some(strict_funds),
+            trade_fee,
+            position_nft: None,
+        },
+    )
+    .unwrap();
+
+    deps
+}
+
+pub fn native(denom: &str, amount: u128) -> OtcItemInfo {
+    OtcItemInfo::Token {
+        denom: denom.to_string(),
+        amount: amount.into(),
+    }
+}
+
+pub fn cw20(contract: &str, amount: u128) -> OtcItemInfo {
+    OtcItemInfo::Cw20 {
+        contract: Addr::unchecked(contract),
+        amount: amount.into(),
+    }
+}
+
+pub fn nft(contract: &str, token_id: &str) -> OtcItemInfo {
+    OtcItemInfo::Cw721 {
+        contract: Addr::unchecked(contract),
+        token_id: token_id.to_string(),
+    }
+}
+
+pub fn registration(item_info: OtcItemInfo) -> OtcItemRegistration {
+    OtcItemRegistration {
+        item_info,
+        vesting: None,
+    }
+}
+
+pub fn fee_coin() -> Coin {
+    Coin::new(FEE_AMOUNT, FEE_DENOM)
+}
+
+/// Env of the block `seconds` after `mock_env`, one block every 5 seconds
+pub fn env_after(seconds: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(seconds);
+    env.block.height += seconds / 5;
+    env
+}
+
+pub fn exec(
+    deps: &mut MockDeps,
+    env: &Env,
+    sender: &str,
+    funds: &[Coin],
+    msg: ExecuteMsg,
+) -> ContractResponse {
+    execute(deps.as_mut(), env.clone(), mock_info(sender, funds), msg)
+}
+
+/// Execute `msg` as sent by `sender` through `Cw20ExecuteMsg::Send` of `amount` `token`
+pub fn exec_cw20(
+    deps: &mut MockDeps,
+    env: &Env,
+    token: &str,
+    sender: &str,
+    amount: u128,
+    msg: &Cw20HookMsg,
+) -> ContractResponse {
+    exec(
+        deps,
+        env,
+        token,
+        &[],
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: amount.into(),
+            msg: to_json_binary(msg).unwrap(),
+        }),
+    )
+}
+
+/// Execute `msg` as sent by `sender` through `Cw721ExecuteMsg::SendNft` of `token_id` of `contract`
+pub fn exec_nft(
+    deps: &mut MockDeps,
+    env: &Env,
+    contract: &str,
+    sender: &str,
+    token_id: &str,
+    msg: &Cw721HookMsg,
+) -> ContractResponse {
+    exec(
+        deps,
+        env,
+        contract,
+        &[],
+        ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            sender: sender.to_string(),
+            token_id: token_id.to_string(),
+            msg: to_json_binary(msg).unwrap(),
+        }),
+    )
+}
+
+/// Answer the cw721 `OwnerOf` queries with `owners` as `(contract, token_id, owner)`, a token not listed is not found
+pub fn mock_nft_owners(deps: &mut MockDeps, owners: &[(&str, &str, &str)]) {
+    let owners: Vec<(String, String, String)> = owners
+        .iter()
+        .map(|(contract, token_id, owner)| {
+            (
+                contract.to_string(),
+                token_id.to_string(),
+                owner.to_string(),
+            )
+        })
+        .collect();
+
+    deps.querier.update_wasm(move |query| match query {
+        WasmQuery::Smart { contract_addr, msg } => match from_json(msg) {
+            Ok(Cw721QueryMsg::OwnerOf { token_id, .. }) => {
+                match owners
+                    .iter()
+                    .find(|(contract, id, _)| contract == contract_addr && *id == token_id)
+                {
+                    Some((_, _, owner)) => SystemResult::Ok(ContractResult::Ok(
+                        to_json_binary(&OwnerOfResponse {
+                            owner: owner.clone(),
+                            approvals: vec![],
+                        })
+                        .unwrap(),
+                    )),
+                    None => {
+                        SystemResult::Ok(ContractResult::Err(format!("token {token_id} not found")))
+                    }
+                }
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "smart".to_string(),
+            }),
+        },
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "wasm".to_string(),
+        }),
+    });
+}
+
+/// Assets moved by the msgs of `res` as `(recipient, item)`, a `Cw20` pulled with `TransferFrom` included
+pub fn sent_items(res: &Response) -> Vec<(String, OtcItemInfo)> {
+    let mut items = vec![];
+
+    for sub_msg in &res.messages {
+        match &sub_msg.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                for coin in amount {
+                    items.push((to_address.clone(), native(&coin.denom, coin.amount.u128())))
+                }
+            }
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                if let Ok(msg) = from_json::<Cw20ExecuteMsg>(msg) {
+                    match msg {
+                        Cw20ExecuteMsg::Transfer { recipient, amount }
+                        | Cw20ExecuteMsg::TransferFrom {
+                            recipient, amount, ..
+                        } => items.push((recipient, cw20(contract_addr, amount.u128()))),
+                        _ => {}
+                    }
+                } else if let Ok(Cw721ExecuteMsg::TransferNft {
+                    recipient,
+                    token_id,
+                }) = from_json::<Cw721ExecuteMsg>(msg)
+                {
+                    items.push((recipient, nft(contract_addr, &token_id)))
+                }
+            }
+            _ => {}
+        }
+    }
+
+    items
+}
+
+pub fn load_config(deps: &MockDeps) -> Config {
+    CONFIG.load(deps.as_ref().storage).unwrap()
+}
+
+pub fn load_position(deps: &MockDeps, id: u64) -> OtcPosition {
+    positions().load(deps.as_ref().storage, id).unwrap()
+}
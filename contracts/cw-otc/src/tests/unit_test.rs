@@ -0,0 +1,689 @@
+use cosmwasm_std::{
+    from_json,
+    testing::{mock_dependencies, mock_env, mock_info},
+    Coin, CosmosMsg, Decimal, WasmMsg,
+};
+use cw_otc_common::{
+    definitions::{
+        Expiration, OtcItemInfo, OtcPosition, OtcPositionStatus, TradeFee, VestingCheckpoint,
+    },
+    error::ContractError,
+    msgs::{
+        CancelOtcMsg, ClaimManyMsg, ClaimOtcMsg, ClaimableItem, CounterOfferMsg, CreateOtcMsg,
+        Cw20HookMsg, Cw721HookMsg, DepositOtcMsg, ExecuteMsg, ExecuteOtcMsg, InstantiateMsg,
+        OtcItemRegistration, PartialFillRegistration, ProposeNewOwnerMsg, QueryMsg,
+        QueryPositionsFilter, QueryPositionsFilterStatus, SetGuardianMsg, SetPauseMsg,
+        SetPayoutAddressMsg, UpdateConfigMsg, UpdateOtcMsg, VestingInfoRegistration,
+    },
+};
+
+use crate::contract::{instantiate, query};
+
+use super::unit_helper::{
+    cw20, env_after, exec, exec_cw20, exec_nft, fee_coin, load_config, load_position,
+    mock_nft_owners, mock_startup, native, nft, registration, sent_items, MockDeps, FEE_COLLECTOR,
+    FEE_DENOM, OWNER,
+};
+
+fn create_msg(offer: Vec<OtcItemInfo>, ask: Vec<OtcItemInfo>) -> CreateOtcMsg {
+    CreateOtcMsg {
+        executors: vec![],
+        offer: offer.into_iter().map(registration).collect(),
+        ask: ask.into_iter().map(registration).collect(),
+        expires_at: None,
+        staged: None,
+        partial_fill: None,
+    }
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_update_config() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let msg = UpdateConfigMsg { fee: Some(vec![native("uatom", 10)]), fee_collector: Some("new_collector".to_string()), strict_funds: Some(true), trade_fee: None, position_nft: None };
+
+    // Only the owner
+
+    let err = exec(&mut deps, &env, "creator", &[], ExecuteMsg::UpdateConfig(msg.clone())).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // The fee items are validated
+
+    let err = exec(&mut deps, &env, OWNER, &[], ExecuteMsg::UpdateConfig(UpdateConfigMsg { fee: Some(vec![cw20("INVALID", 10)]), ..msg.clone() })).unwrap_err();
+    assert!(matches!(err, ContractError::Std(..)));
+    assert_eq!(load_config(&deps).fee, vec![native("uotc", 100)]);
+
+    exec(&mut deps, &env, OWNER, &[], ExecuteMsg::UpdateConfig(msg)).unwrap();
+
+    let config = load_config(&deps);
+    assert_eq!(config.fee, vec![native("uatom", 10)]);
+    assert_eq!(config.fee_collector.as_str(), "new_collector");
+    assert!(config.strict_funds);
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_ownership_transfer() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let propose = |owner: Option<&str>| ExecuteMsg::ProposeNewOwner(ProposeNewOwnerMsg { owner: owner.map(|val| val.to_string()) });
+
+    // Only the owner can propose
+
+    let err = exec(&mut deps, &env, "new_owner", &[], propose(Some("new_owner"))).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // Nothing to accept without a proposal
+
+    let err = exec(&mut deps, &env, "new_owner", &[], ExecuteMsg::AcceptOwnership {}).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    exec(&mut deps, &env, OWNER, &[], propose(Some("new_owner"))).unwrap();
+    assert_eq!(load_config(&deps).pending_owner.unwrap().as_str(), "new_owner");
+    assert_eq!(load_config(&deps).owner.as_str(), OWNER);
+
+    // A proposal can be cancelled
+
+    exec(&mut deps, &env, OWNER, &[], propose(None)).unwrap();
+    assert_eq!(load_config(&deps).pending_owner, None);
+
+    let err = exec(&mut deps, &env, "new_owner", &[], ExecuteMsg::AcceptOwnership {}).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // Only the pending owner can accept
+
+    exec(&mut deps, &env, OWNER, &[], propose(Some("new_owner"))).unwrap();
+
+    let err = exec(&mut deps, &env, "creator", &[], ExecuteMsg::AcceptOwnership {}).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    exec(&mut deps, &env, "new_owner", &[], ExecuteMsg::AcceptOwnership {}).unwrap();
+
+    let config = load_config(&deps);
+    assert_eq!(config.owner.as_str(), "new_owner");
+    assert_eq!(config.pending_owner, None);
+
+    // The previous owner lost its rights
+
+    let err = exec(&mut deps, &env, OWNER, &[], propose(Some(OWNER))).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_refund_surplus() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let msg = create_msg(vec![native("uatom", 1_000)], vec![native("uusd", 500)]);
+
+    // Surplus of the offer and fee denoms and unexpected denoms are refunded
+
+    let funds = vec![Coin::new(1_200, "uatom"), Coin::new(150, FEE_DENOM), Coin::new(7, "ujunk")];
+    let res = exec(&mut deps, &env, "creator", &funds, ExecuteMsg::CreateOtc(msg.clone())).unwrap();
+
+    let sent = sent_items(&res);
+    assert!(sent.contains(&(FEE_COLLECTOR.to_string(), native(FEE_DENOM, 100))));
+    assert!(sent.contains(&("creator".to_string(), native("uatom", 200))));
+    assert!(sent.contains(&("creator".to_string(), native(FEE_DENOM, 50))));
+    assert!(sent.contains(&("creator".to_string(), native("ujunk", 7))));
+    assert_eq!(sent.len(), 4);
+
+    // Nothing to refund with the exact funds
+
+    let funds = vec![Coin::new(1_000, "uatom"), fee_coin()];
+    let res = exec(&mut deps, &env, "creator", &funds, ExecuteMsg::CreateOtc(msg.clone())).unwrap();
+    assert_eq!(sent_items(&res), vec![(FEE_COLLECTOR.to_string(), native(FEE_DENOM, 100))]);
+
+    // Executing with a surplus of the ask
+
+    let funds = vec![Coin::new(600, "uusd"), fee_coin()];
+    let res = exec(&mut deps, &env, "executor", &funds, ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: None })).unwrap();
+
+    let sent = sent_items(&res);
+    assert!(sent.contains(&("executor".to_string(), native("uusd", 100))));
+    assert!(sent.contains(&("creator".to_string(), native("uusd", 500))));
+    assert!(sent.contains(&("executor".to_string(), native("uatom", 1_000))));
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_strict_funds() {
+    let mut deps = mock_startup(true, None);
+    let env = mock_env();
+
+    let msg = create_msg(vec![native("uatom", 1_000)], vec![native("uusd", 500)]);
+
+    let funds = vec![Coin::new(1_000, "uatom"), fee_coin(), Coin::new(7, "ujunk")];
+    let err = exec(&mut deps, &env, "creator", &funds, ExecuteMsg::CreateOtc(msg.clone())).unwrap_err();
+    assert_eq!(err, ContractError::UnexpectedDenom { denom: "ujunk".to_string() });
+
+    let err = exec_cw20(&mut deps, &env, "other_token", "creator", 10, &Cw20HookMsg::CreateOtc(msg.clone())).unwrap_err();
+    assert_eq!(err, ContractError::UnexpectedCw20 { contract: "other_token".to_string() });
+
+    let err = exec_nft(&mut deps, &env, "other_nft", "creator", "1", &Cw721HookMsg::CreateOtc(msg.clone())).unwrap_err();
+    assert_eq!(err, ContractError::UnexpectedNft { contract: "other_nft".to_string(), token_id: "1".to_string() });
+
+    // A surplus of an expected denom is still refunded
+
+    let funds = vec![Coin::new(1_100, "uatom"), fee_coin()];
+    let res = exec(&mut deps, &env, "creator", &funds, ExecuteMsg::CreateOtc(msg)).unwrap();
+    assert!(sent_items(&res).contains(&("creator".to_string(), native("uatom", 100))));
+
+    // Also on execution
+
+    let funds = vec![Coin::new(500, "uusd"), fee_coin(), Coin::new(1_000, "uatom")];
+    let err = exec(&mut deps, &env, "executor", &funds, ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: None })).unwrap_err();
+    assert_eq!(err, ContractError::UnexpectedDenom { denom: "uatom".to_string() });
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_trade_fee() {
+    let mut deps = mock_startup(false, Some(TradeFee { maker_bps: 100, taker_bps: 200 }));
+    let env = mock_env();
+
+    // Taken from the items released on execution, maker from the ask and taker from the offer
+
+    let msg = create_msg(vec![native("uatom", 10_000)], vec![native("uusd", 5_000)]);
+    exec(&mut deps, &env, "creator", &[Coin::new(10_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    let res = exec(&mut deps, &env, "executor", &[Coin::new(5_000, "uusd"), fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: None })).unwrap();
+
+    let sent = sent_items(&res);
+    assert!(sent.contains(&("creator".to_string(), native("uusd", 4_950))));
+    assert!(sent.contains(&(FEE_COLLECTOR.to_string(), native("uusd", 50))));
+    assert!(sent.contains(&("executor".to_string(), native("uatom", 9_800))));
+    assert!(sent.contains(&(FEE_COLLECTOR.to_string(), native("uatom", 200))));
+
+    // Taken on every vesting claim
+
+    let mut msg = create_msg(vec![], vec![native("uusd", 5_000)]);
+    msg.offer = vec![OtcItemRegistration { item_info: native("uatom", 10_000), vesting: Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: None }) }];
+    exec(&mut deps, &env, "creator", &[Coin::new(10_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    let res = exec(&mut deps, &env, "executor", &[Coin::new(5_000, "uusd"), fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 2, fill: None })).unwrap();
+    assert!(!sent_items(&res).iter().any(|(_, item)| *item == native("uatom", 9_800)));
+
+    let res = exec(&mut deps, &env_after(500), "executor", &[], ExecuteMsg::ClaimOtc(ClaimOtcMsg { id: 2, recipient: None })).unwrap();
+    assert_eq!(sent_items(&res), vec![("executor".to_string(), native("uatom", 4_900)), (FEE_COLLECTOR.to_string(), native("uatom", 100))]);
+
+    let res = exec(&mut deps, &env_after(1_000), "executor", &[], ExecuteMsg::ClaimOtc(ClaimOtcMsg { id: 2, recipient: None })).unwrap();
+    assert_eq!(sent_items(&res), vec![("executor".to_string(), native("uatom", 4_900)), (FEE_COLLECTOR.to_string(), native("uatom", 100))]);
+
+    // Taken on each partial fill
+
+    let mut msg = create_msg(vec![native("uatom", 10_000)], vec![native("uusd", 5_000)]);
+    msg.partial_fill = Some(PartialFillRegistration { min_fill: Decimal::percent(10) });
+    exec(&mut deps, &env, "creator", &[Coin::new(10_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    let res = exec(&mut deps, &env, "executor", &[Coin::new(2_500, "uusd"), fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 3, fill: Some(Decimal::percent(50)) })).unwrap();
+
+    let sent = sent_items(&res);
+    assert!(sent.contains(&("creator".to_string(), native("uusd", 2_475))));
+    assert!(sent.contains(&(FEE_COLLECTOR.to_string(), native("uusd", 25))));
+    assert!(sent.contains(&("executor".to_string(), native("uatom", 4_900))));
+    assert!(sent.contains(&(FEE_COLLECTOR.to_string(), native("uatom", 100))));
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_trade_fee_validation() {
+    let mut deps = mock_dependencies();
+
+    let msg = InstantiateMsg { owner: OWNER.to_string(), fee: vec![], fee_collector: FEE_COLLECTOR.to_string(), strict_funds: None, trade_fee: Some(TradeFee { maker_bps: 10_001, taker_bps: 0 }), position_nft: None };
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::InvalidTradeFee { max_bps: 10_000 });
+
+    let mut deps = mock_startup(false, Some(TradeFee { maker_bps: 10_000, taker_bps: 10_000 }));
+
+    let msg = UpdateConfigMsg { fee: None, fee_collector: None, strict_funds: None, trade_fee: Some(TradeFee { maker_bps: 0, taker_bps: 10_001 }), position_nft: None };
+    let err = exec(&mut deps, &mock_env(), OWNER, &[], ExecuteMsg::UpdateConfig(msg)).unwrap_err();
+    assert_eq!(err, ContractError::InvalidTradeFee { max_bps: 10_000 });
+    assert_eq!(load_config(&deps).trade_fee, TradeFee { maker_bps: 10_000, taker_bps: 10_000 });
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_expiration() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let expires_at = Expiration::AtTime(env.block.time.seconds() + 100);
+    let funds = vec![Coin::new(1_000, "uatom"), fee_coin()];
+
+    let mut msg = create_msg(vec![native("uatom", 1_000)], vec![native("uusd", 500)]);
+    msg.executors = vec!["executor".to_string()];
+
+    // Already expired on creation
+
+    msg.expires_at = Some(Expiration::AtTime(env.block.time.seconds()));
+    let err = exec(&mut deps, &env, "creator", &funds, ExecuteMsg::CreateOtc(msg.clone())).unwrap_err();
+    assert_eq!(err, ContractError::ExpirationPassed { expires_at: Expiration::AtTime(env.block.time.seconds()) });
+
+    msg.expires_at = Some(expires_at.clone());
+    exec(&mut deps, &env, "creator", &funds, ExecuteMsg::CreateOtc(msg)).unwrap();
+    assert_eq!(load_position(&deps, 1).expires_at, Some(expires_at));
+
+    // Neither executed nor deposited after the deadline
+
+    let expired_env = env_after(100);
+    let funds = vec![Coin::new(500, "uusd"), fee_coin()];
+
+    let err = exec(&mut deps, &expired_env, "executor", &funds, ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: None })).unwrap_err();
+    assert_eq!(err, ContractError::PositionExpired { id: 1 });
+
+    let err = exec(&mut deps, &expired_env, "executor", &funds, ExecuteMsg::DepositOtc(DepositOtcMsg { id: 1, items: None })).unwrap_err();
+    assert_eq!(err, ContractError::PositionExpired { id: 1 });
+
+    let qy_ids = |deps: &MockDeps, status: QueryPositionsFilterStatus| {
+        let filters = QueryPositionsFilter { owner: None, executor: None, status: Some(status) };
+        let positions: Vec<OtcPosition> = from_json(query(deps.as_ref(), expired_env.clone(), QueryMsg::Positions { limit: None, start_after: None, filters: Some(filters), order: None }).unwrap()).unwrap();
+        positions.iter().map(|val| val.id).collect::<Vec<u64>>()
+    };
+
+    // Expired but not reclaimed yet, still listed as pending
+
+    assert_eq!(load_position(&deps, 1).status, OtcPositionStatus::Pending);
+    assert_eq!(qy_ids(&deps, QueryPositionsFilterStatus::Pending), vec![1]);
+    assert_eq!(qy_ids(&deps, QueryPositionsFilterStatus::Expired), Vec::<u64>::new());
+
+    // The owner reclaims the offer
+
+    let res = exec(&mut deps, &expired_env, "creator", &[], ExecuteMsg::CancelOtc(CancelOtcMsg { id: 1 })).unwrap();
+    assert_eq!(sent_items(&res), vec![("creator".to_string(), native("uatom", 1_000))]);
+    assert_eq!(load_position(&deps, 1).status, OtcPositionStatus::Expired(expired_env.block.time.seconds()));
+
+    assert_eq!(qy_ids(&deps, QueryPositionsFilterStatus::Pending), Vec::<u64>::new());
+    assert_eq!(qy_ids(&deps, QueryPositionsFilterStatus::Expired), vec![1]);
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_cw20_hook() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    // Cw20 can't be sent along with the native fee
+    exec(&mut deps, &env, OWNER, &[], ExecuteMsg::UpdateConfig(UpdateConfigMsg { fee: Some(vec![]), fee_collector: None, strict_funds: None, trade_fee: None, position_nft: None })).unwrap();
+
+    // Create with the offer sent, the surplus is refunded
+
+    let msg = create_msg(vec![cw20("offer_token", 1_000)], vec![native("uusd", 500)]);
+    let res = exec_cw20(&mut deps, &env, "offer_token", "creator", 1_200, &Cw20HookMsg::CreateOtc(msg)).unwrap();
+    assert_eq!(sent_items(&res), vec![("creator".to_string(), cw20("offer_token", 200))]);
+
+    let position = load_position(&deps, 1);
+    assert_eq!(position.owner.as_str(), "creator");
+    assert!(position.offer[0].deposited);
+
+    // Execute with the ask sent, the surplus is refunded
+
+    let msg = create_msg(vec![native("uatom", 1_000)], vec![cw20("ask_token", 500)]);
+    exec(&mut deps, &env, "creator", &[Coin::new(1_000, "uatom")], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    let res = exec_cw20(&mut deps, &env, "ask_token", "executor", 600, &Cw20HookMsg::ExecuteOtc(ExecuteOtcMsg { id: 2, fill: None })).unwrap();
+    let sent = sent_items(&res);
+    assert_eq!(sent.len(), 3);
+    assert!(sent.contains(&("executor".to_string(), native("uatom", 1_000))));
+    assert!(sent.contains(&("creator".to_string(), cw20("ask_token", 500))));
+    assert!(sent.contains(&("executor".to_string(), cw20("ask_token", 100))));
+    assert!(matches!(load_position(&deps, 2).status, OtcPositionStatus::Executed(..)));
+
+    // A mismatched Cw20 contract is rejected under strict funds
+
+    exec(&mut deps, &env, OWNER, &[], ExecuteMsg::UpdateConfig(UpdateConfigMsg { fee: None, fee_collector: None, strict_funds: Some(true), trade_fee: None, position_nft: None })).unwrap();
+
+    let msg = create_msg(vec![native("uatom", 1_000)], vec![cw20("ask_token", 500)]);
+    exec(&mut deps, &env, "creator", &[Coin::new(1_000, "uatom")], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    let err = exec_cw20(&mut deps, &env, "other_token", "executor", 500, &Cw20HookMsg::ExecuteOtc(ExecuteOtcMsg { id: 3, fill: None })).unwrap_err();
+    assert_eq!(err, ContractError::UnexpectedCw20 { contract: "other_token".to_string() });
+
+    exec_cw20(&mut deps, &env, "ask_token", "executor", 500, &Cw20HookMsg::ExecuteOtc(ExecuteOtcMsg { id: 3, fill: None })).unwrap();
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_pull_nft_owner() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let contract = env.contract.address.to_string();
+    mock_nft_owners(&mut deps, &[("nft_contract", "1", contract.as_str()), ("nft_contract", "2", "creator")]);
+
+    // An nft escrowed by the contract can't be pulled
+
+    let msg = create_msg(vec![nft("nft_contract", "1")], vec![native("uusd", 500)]);
+    let err = exec(&mut deps, &env, "attacker", &[fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap_err();
+    assert_eq!(err, ContractError::NftNotOwned { token_id: "1".to_string(), owner: "attacker".to_string() });
+
+    let msg = create_msg(vec![native("uusd", 500)], vec![nft("nft_contract", "1")]);
+    exec(&mut deps, &env, "creator", &[Coin::new(500, "uusd"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+    let err = exec(&mut deps, &env, "attacker", &[fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: None })).unwrap_err();
+    assert_eq!(err, ContractError::NftNotOwned { token_id: "1".to_string(), owner: "attacker".to_string() });
+
+    // Pulled from its owner
+
+    let msg = create_msg(vec![nft("nft_contract", "2")], vec![native("uusd", 500)]);
+    let res = exec(&mut deps, &env, "creator", &[fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+    assert!(sent_items(&res).contains(&(contract, nft("nft_contract", "2"))));
+    assert!(load_position(&deps, 2).offer[0].deposited);
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_staged_fee_paid_once() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let mut msg = create_msg(vec![native("uatom", 1_000)], vec![native("uusd", 500), native("ujuno", 300)]);
+    msg.executors = vec!["executor".to_string()];
+    exec(&mut deps, &env, "creator", &[Coin::new(1_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    // The fee is paid with the first deposit
+
+    let deposit_msg = ExecuteMsg::DepositOtc(DepositOtcMsg { id: 1, items: Some(vec![0]) });
+    let res = exec(&mut deps, &env, "executor", &[Coin::new(500, "uusd"), fee_coin()], deposit_msg.clone()).unwrap();
+    assert_eq!(sent_items(&res), vec![(FEE_COLLECTOR.to_string(), native(FEE_DENOM, 100))]);
+    assert!(load_position(&deps, 1).fee_paid);
+
+    // Withdrawing the deposits doesn't refund the fee, nor charges it again
+
+    let res = exec(&mut deps, &env, "executor", &[], ExecuteMsg::CancelOtc(CancelOtcMsg { id: 1 })).unwrap();
+    assert_eq!(sent_items(&res), vec![("executor".to_string(), native("uusd", 500))]);
+
+    let res = exec(&mut deps, &env, "executor", &[Coin::new(500, "uusd")], deposit_msg).unwrap();
+    assert_eq!(sent_items(&res), vec![]);
+
+    let res = exec(&mut deps, &env, "executor", &[Coin::new(300, "ujuno")], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: None })).unwrap();
+    assert!(!sent_items(&res).iter().any(|(recipient, _)| recipient == FEE_COLLECTOR));
+    assert!(matches!(load_position(&deps, 1).status, OtcPositionStatus::Executed(..)));
+
+    // A different executor pays its own fee
+
+    let mut msg = create_msg(vec![native("uatom", 1_000)], vec![native("uusd", 500), native("ujuno", 400)]);
+    msg.executors = vec!["executor".to_string()];
+    exec(&mut deps, &env, "creator", &[Coin::new(1_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    exec(&mut deps, &env, "executor", &[Coin::new(400, "ujuno"), fee_coin()], ExecuteMsg::DepositOtc(DepositOtcMsg { id: 2, items: Some(vec![1]) })).unwrap();
+    exec(&mut deps, &env, "executor", &[], ExecuteMsg::CancelOtc(CancelOtcMsg { id: 2 })).unwrap();
+    assert!(load_position(&deps, 2).fee_paid);
+    exec(&mut deps, &env, "creator", &[], ExecuteMsg::UpdateOtc(UpdateOtcMsg { id: 2, executors: Some(vec!["other".to_string()]), offer: None, ask: None, expires_at: None })).unwrap();
+    assert!(!load_position(&deps, 2).fee_paid);
+
+    let funds = vec![Coin::new(500, "uusd"), Coin::new(400, "ujuno")];
+    let err = exec(&mut deps, &env, "other", &funds, ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 2, fill: None })).unwrap_err();
+    assert_eq!(err, ContractError::CoinNotReceived { denom: FEE_DENOM.to_string() });
+
+    let funds = vec![Coin::new(500, "uusd"), Coin::new(400, "ujuno"), fee_coin()];
+    exec(&mut deps, &env, "other", &funds, ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 2, fill: None })).unwrap();
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_fill_rounding_to_zero() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let mut msg = create_msg(vec![native("uatom", 10_000)], vec![native("uusd", 3)]);
+    msg.partial_fill = Some(PartialFillRegistration { min_fill: Decimal::percent(1) });
+    exec(&mut deps, &env, "creator", &[Coin::new(10_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    // 10% of the ask rounds to 0, the offer can't be taken for free
+
+    let err = exec(&mut deps, &env, "executor", &[Coin::new(1, "uusd"), fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: Some(Decimal::percent(10)) })).unwrap_err();
+    assert_eq!(err, ContractError::FillTooSmall { fill: Decimal::percent(10) });
+
+    let res = exec(&mut deps, &env, "executor", &[Coin::new(1, "uusd"), fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: Some(Decimal::percent(40)) })).unwrap();
+    let sent = sent_items(&res);
+    assert!(sent.contains(&("creator".to_string(), native("uusd", 1))));
+    assert!(sent.contains(&("executor".to_string(), native("uatom", 4_000))));
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_executor_payout_reset() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let mut msg = create_msg(vec![native("uatom", 1_000)], vec![native("uusd", 500)]);
+    msg.executors = vec!["executor".to_string()];
+    exec(&mut deps, &env, "creator", &[Coin::new(1_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    let payout_msg = ExecuteMsg::SetPayoutAddress(SetPayoutAddressMsg { id: 1, address: Some("payout".to_string()) });
+    exec(&mut deps, &env, "executor", &[], payout_msg.clone()).unwrap();
+
+    let update_msg = |executors: &[&str]| ExecuteMsg::UpdateOtc(UpdateOtcMsg { id: 1, executors: Some(executors.iter().map(|val| val.to_string()).collect()), offer: None, ask: None, expires_at: None });
+
+    // Kept if the executor doesn't change
+
+    exec(&mut deps, &env, "creator", &[], update_msg(&["executor"])).unwrap();
+    assert_eq!(load_position(&deps, 1).executor_payout.unwrap().as_str(), "payout");
+
+    // Reset for a different executor
+
+    exec(&mut deps, &env, "creator", &[], update_msg(&["other"])).unwrap();
+    let position = load_position(&deps, 1);
+    assert_eq!(position.executor.unwrap().as_str(), "other");
+    assert_eq!(position.executor_payout, None);
+
+    exec(&mut deps, &env, "creator", &[], update_msg(&["executor"])).unwrap();
+    exec(&mut deps, &env, "executor", &[], payout_msg).unwrap();
+
+    exec(&mut deps, &env, "creator", &[], update_msg(&["executor", "other"])).unwrap();
+    let position = load_position(&deps, 1);
+    assert_eq!(position.executor, None);
+    assert_eq!(position.executor_payout, None);
+
+    let res = exec(&mut deps, &env, "other", &[Coin::new(500, "uusd"), fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: None })).unwrap();
+    assert!(sent_items(&res).contains(&("other".to_string(), native("uatom", 1_000))));
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_staged_execution_position_nft() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    exec(&mut deps, &env, OWNER, &[], ExecuteMsg::UpdateConfig(UpdateConfigMsg { fee: None, fee_collector: None, strict_funds: None, trade_fee: None, position_nft: Some("position_nft".to_string()) })).unwrap();
+
+    let mut msg = create_msg(vec![], vec![native("uusd", 500), native("ujuno", 300)]);
+    msg.offer = vec![OtcItemRegistration { item_info: native("uatom", 10_000), vesting: Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: None }) }];
+    msg.executors = vec!["executor".to_string()];
+    exec(&mut deps, &env, "creator", &[Coin::new(10_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    // The side tokens are minted when the last deposit executes the position
+
+    exec(&mut deps, &env, "executor", &[Coin::new(500, "uusd"), fee_coin()], ExecuteMsg::DepositOtc(DepositOtcMsg { id: 1, items: Some(vec![0]) })).unwrap();
+    let res = exec(&mut deps, &env, "executor", &[Coin::new(300, "ujuno")], ExecuteMsg::DepositOtc(DepositOtcMsg { id: 1, items: Some(vec![1]) })).unwrap();
+
+    let mints = res.messages.iter().filter(|val| matches!(&val.msg, CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) if contract_addr == "position_nft")).count();
+    assert_eq!(mints, 2);
+    assert_eq!(load_position(&deps, 1).position_nft.unwrap().as_str(), "position_nft");
+
+    // A side token not found doesn't prevent the holder of the other one from claiming
+
+    mock_nft_owners(&mut deps, &[("position_nft", "1-executor", "executor")]);
+
+    let res = exec(&mut deps, &env_after(500), "executor", &[], ExecuteMsg::ClaimOtc(ClaimOtcMsg { id: 1, recipient: None })).unwrap();
+    assert_eq!(sent_items(&res), vec![("executor".to_string(), native("uatom", 5_000))]);
+
+    let err = exec(&mut deps, &env_after(500), "creator", &[], ExecuteMsg::ClaimOtc(ClaimOtcMsg { id: 1, recipient: None })).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_pause_deposit() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let pause = |create: bool, execute: bool| ExecuteMsg::SetPause(SetPauseMsg { create: Some(create), execute: Some(execute), claim: None, cancel: None });
+
+    // Funding position, the offer is deposited later
+    let mut msg = create_msg(vec![native("uatom", 1_000)], vec![native("uusd", 500)]);
+    msg.staged = Some(true);
+    msg.executors = vec!["executor".to_string()];
+    exec(&mut deps, &env, "creator", &[fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+    assert_eq!(load_position(&deps, 1).status, OtcPositionStatus::Funding);
+
+    let deposit_msg = ExecuteMsg::DepositOtc(DepositOtcMsg { id: 1, items: None });
+
+    // Funding is paused with the creation
+
+    exec(&mut deps, &env, OWNER, &[], pause(true, false)).unwrap();
+    let err = exec(&mut deps, &env, "creator", &[Coin::new(1_000, "uatom")], deposit_msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Paused { .. }));
+
+    exec(&mut deps, &env, OWNER, &[], pause(false, true)).unwrap();
+    exec(&mut deps, &env, "creator", &[Coin::new(1_000, "uatom")], deposit_msg.clone()).unwrap();
+    assert_eq!(load_position(&deps, 1).status, OtcPositionStatus::Pending);
+
+    // A staged ask deposit is paused with the execution
+
+    let err = exec(&mut deps, &env, "executor", &[Coin::new(500, "uusd"), fee_coin()], deposit_msg.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Paused { .. }));
+
+    // So is a counter offer
+
+    let counter_msg = ExecuteMsg::CounterOffer(CounterOfferMsg { id: 1, ask: vec![registration(native("uusd", 400))] });
+    let err = exec(&mut deps, &env, "proposer", &[Coin::new(400, "uusd"), fee_coin()], counter_msg).unwrap_err();
+    assert!(matches!(err, ContractError::Paused { .. }));
+
+    // Topping up the offer is paused with the creation, the other updates are not
+
+    exec(&mut deps, &env, OWNER, &[], pause(true, true)).unwrap();
+
+    let update_msg = UpdateOtcMsg { id: 1, executors: None, offer: Some(vec![registration(native("uatom", 1_100))]), ask: None, expires_at: None };
+    let err = exec(&mut deps, &env, "creator", &[Coin::new(100, "uatom")], ExecuteMsg::UpdateOtc(update_msg.clone())).unwrap_err();
+    assert!(matches!(err, ContractError::Paused { .. }));
+
+    exec(&mut deps, &env, "creator", &[], ExecuteMsg::UpdateOtc(UpdateOtcMsg { offer: None, executors: Some(vec!["executor".to_string()]), ..update_msg })).unwrap();
+
+    exec(&mut deps, &env, OWNER, &[], pause(false, false)).unwrap();
+    exec(&mut deps, &env, "executor", &[Coin::new(500, "uusd"), fee_coin()], deposit_msg).unwrap();
+    assert!(matches!(load_position(&deps, 1).status, OtcPositionStatus::Executed(..)));
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_set_guardian() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let set_guardian = |guardian: Option<&str>| ExecuteMsg::SetGuardian(SetGuardianMsg { guardian: guardian.map(|val| val.to_string()) });
+    let pause = ExecuteMsg::SetPause(SetPauseMsg { create: Some(true), execute: None, claim: None, cancel: None });
+
+    let err = exec(&mut deps, &env, "guardian", &[], set_guardian(Some("guardian"))).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    exec(&mut deps, &env, OWNER, &[], set_guardian(Some("guardian"))).unwrap();
+    assert_eq!(load_config(&deps).guardian.unwrap().as_str(), "guardian");
+    exec(&mut deps, &env, "guardian", &[], pause.clone()).unwrap();
+
+    // Removed
+
+    exec(&mut deps, &env, OWNER, &[], set_guardian(None)).unwrap();
+    assert_eq!(load_config(&deps).guardian, None);
+
+    let err = exec(&mut deps, &env, "guardian", &[], pause).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_vesting_start_at() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    // 10% at the start, a week after the execution
+    let start_at = env.block.time.seconds() + 604_800;
+    let checkpoints = vec![VestingCheckpoint { time: 0, percentage: Decimal::percent(10) }, VestingCheckpoint { time: 100, percentage: Decimal::one() }];
+
+    let mut msg = create_msg(vec![], vec![native("uusd", 500)]);
+    msg.offer = vec![OtcItemRegistration { item_info: native("uatom", 10_000), vesting: Some(VestingInfoRegistration::Custom { checkpoints, clock: None, start_at: Some(start_at) }) }];
+    exec(&mut deps, &env, "creator", &[Coin::new(10_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+
+    // Nothing released before the start
+
+    let res = exec(&mut deps, &env, "executor", &[Coin::new(500, "uusd"), fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: None })).unwrap();
+    assert!(!sent_items(&res).iter().any(|(recipient, _)| recipient == "executor"));
+
+    let err = exec(&mut deps, &env_after(604_799), "executor", &[], ExecuteMsg::ClaimOtc(ClaimOtcMsg { id: 1, recipient: None })).unwrap_err();
+    assert_eq!(err, ContractError::NothingToClaim {});
+
+    let res = exec(&mut deps, &env_after(604_800), "executor", &[], ExecuteMsg::ClaimOtc(ClaimOtcMsg { id: 1, recipient: None })).unwrap();
+    assert_eq!(sent_items(&res), vec![("executor".to_string(), native("uatom", 1_000))]);
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_claim_many_limits() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    let vesting = Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: None });
+
+    for id in 1..=4 {
+        let mut msg = create_msg(vec![], vec![native("uusd", 10)]);
+        // The last one is executed without vesting
+        msg.offer = vec![OtcItemRegistration { item_info: native("uatom", 1_000), vesting: if id < 4 { vesting.clone() } else { None } }];
+        exec(&mut deps, &env, "creator", &[Coin::new(1_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+        exec(&mut deps, &env, "executor", &[Coin::new(10, "uusd"), fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id, fill: None })).unwrap();
+    }
+
+    assert!(matches!(load_position(&deps, 4).status, OtcPositionStatus::Executed(..)));
+
+    let claimed = |res: &cosmwasm_std::Response| sent_items(res).into_iter().filter(|(recipient, _)| recipient == "executor").map(|(_, item)| item.get_amount().u128()).sum::<u128>();
+
+    // The limit applies once to the positions found as owner and executor
+
+    let res = exec(&mut deps, &env_after(500), "executor", &[], ExecuteMsg::ClaimMany(ClaimManyMsg { ids: None, limit: Some(2) })).unwrap();
+    assert_eq!(claimed(&res), 1_000);
+    assert_eq!(load_position(&deps, 3).offer[0].vesting_info.clone().unwrap().claimed.u128(), 0);
+
+    // An executed position is skipped
+
+    let res = exec(&mut deps, &env_after(500), "executor", &[], ExecuteMsg::ClaimMany(ClaimManyMsg { ids: Some(vec![3, 4]), limit: None })).unwrap();
+    assert_eq!(claimed(&res), 500);
+
+    // Explicit ids are bounded
+
+    let err = exec(&mut deps, &env_after(500), "executor", &[], ExecuteMsg::ClaimMany(ClaimManyMsg { ids: Some((1..=31).collect()), limit: None })).unwrap_err();
+    assert_eq!(err, ContractError::TooManyPositions { max: 30 });
+}
+
+#[test]
+#[rustfmt::skip]
+pub fn test_claim_both_sides() {
+    let mut deps = mock_startup(false, None);
+    let env = mock_env();
+
+    exec(&mut deps, &env, OWNER, &[], ExecuteMsg::UpdateConfig(UpdateConfigMsg { fee: None, fee_collector: None, strict_funds: None, trade_fee: None, position_nft: Some("position_nft".to_string()) })).unwrap();
+
+    let vesting = Some(VestingInfoRegistration::Linear { cliff: None, vesting: Some(1_000), clock: None, start_at: None });
+    let mut msg = create_msg(vec![], vec![]);
+    msg.offer = vec![OtcItemRegistration { item_info: native("uatom", 10_000), vesting: vesting.clone() }];
+    msg.ask = vec![OtcItemRegistration { item_info: native("uusd", 500), vesting }];
+    exec(&mut deps, &env, "creator", &[Coin::new(10_000, "uatom"), fee_coin()], ExecuteMsg::CreateOtc(msg)).unwrap();
+    exec(&mut deps, &env, "executor", &[Coin::new(500, "uusd"), fee_coin()], ExecuteMsg::ExecuteOtc(ExecuteOtcMsg { id: 1, fill: None })).unwrap();
+
+    // The buyer of both side tokens claims both sides
+
+    mock_nft_owners(&mut deps, &[("position_nft", "1-owner", "buyer"), ("position_nft", "1-executor", "buyer")]);
+
+    let claimable: Vec<ClaimableItem> = from_json(query(deps.as_ref(), env_after(500), QueryMsg::Claimable { id: 1, address: "buyer".to_string(), at_time: None, at_height: None }).unwrap()).unwrap();
+    assert_eq!(claimable.iter().map(|val| (val.item_info.clone(), val.claimable.u128())).collect::<Vec<_>>(), vec![(native("uusd", 500), 250), (native("uatom", 10_000), 5_000)]);
+
+    let res = exec(&mut deps, &env_after(500), "buyer", &[], ExecuteMsg::ClaimOtc(ClaimOtcMsg { id: 1, recipient: None })).unwrap();
+    assert_eq!(sent_items(&res), vec![("buyer".to_string(), native("uusd", 250)), ("buyer".to_string(), native("uatom", 5_000))]);
+
+    let res = exec(&mut deps, &env_after(1_000), "buyer", &[], ExecuteMsg::ClaimMany(ClaimManyMsg { ids: Some(vec![1]), limit: None })).unwrap();
+    let mut sent = sent_items(&res);
+    sent.sort_by_key(|(_, item)| item.get_amount());
+    assert_eq!(sent, vec![("buyer".to_string(), native("uusd", 250)), ("buyer".to_string(), native("uatom", 5_000))]);
+}
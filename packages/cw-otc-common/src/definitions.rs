@@ -1,18 +1,32 @@
-use std::cmp::min;
+use std::fmt;
 
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    wasm_execute, Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, Env, StdError, StdResult, Uint128,
+    wasm_execute, Addr, BankMsg, BlockInfo, Coin, CosmosMsg, Decimal, Deps, Env, StdResult, Uint128,
 };
 
-use super::msgs::{CreateOtcMsg, OtcItemRegistration, VestingInfoRegistration};
+use crate::error::{ContractError, ContractResult};
+
+use super::msgs::{
+    ClaimableItem, CounterOfferMsg, CreateOtcMsg, OtcItemRegistration, PartialFillRegistration,
+    VestingInfoRegistration,
+};
 
 #[cw_serde]
 pub struct Config {
     pub owner: Addr,
+    pub pending_owner: Option<Addr>,
     pub counter_otc: u64,
     pub fee: Vec<OtcItemInfo>,
     pub fee_collector: Addr,
+    /// If `true`, native coins with a denom not required by the action are rejected instead of refunded
+    pub strict_funds: bool,
+    pub trade_fee: TradeFee,
+    /// Cw721 contract minting a token for each side of a position entering `Vesting`, the otc contract must be its minter
+    pub position_nft: Option<Addr>,
+    /// Can pause actions but not unpause them
+    pub guardian: Option<Addr>,
+    pub pause: PauseFlags,
 }
 
 impl Config {
@@ -21,70 +35,213 @@ impl Config {
         owner: Addr,
         fee: Vec<OtcItemInfo>,
         fee_collector: Addr,
-    ) -> StdResult<Config> {
-        for i in &fee {
-            i.validate(deps)?;
-        }
+        strict_funds: bool,
+        trade_fee: TradeFee,
+        position_nft: Option<Addr>,
+    ) -> ContractResult<Config> {
+        Config::validate_fee(deps, &fee)?;
+        trade_fee.validate()?;
 
         Ok(Config {
             owner,
+            pending_owner: None,
             counter_otc: 0,
             fee,
             fee_collector,
+            strict_funds,
+            trade_fee,
+            position_nft,
+            guardian: None,
+            pause: PauseFlags::default(),
         })
     }
+
+    pub fn assert_not_paused(&self, action: PauseAction) -> ContractResult<()> {
+        if self.pause.is_paused(&action) {
+            return Err(ContractError::Paused { action });
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_fee(deps: Deps, fee: &[OtcItemInfo]) -> StdResult<()> {
+        for i in fee {
+            i.validate(deps)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Actions blocked by the emergency pause, nothing is paused by default
+#[cw_serde]
+#[derive(Default)]
+pub struct PauseFlags {
+    pub create: bool,
+    pub execute: bool,
+    pub claim: bool,
+    pub cancel: bool,
+}
+
+impl PauseFlags {
+    pub fn is_paused(&self, action: &PauseAction) -> bool {
+        match action {
+            PauseAction::Create => self.create,
+            PauseAction::Execute => self.execute,
+            PauseAction::Claim => self.claim,
+            PauseAction::Cancel => self.cancel,
+        }
+    }
+
+    pub fn set(&mut self, action: &PauseAction, paused: bool) {
+        match action {
+            PauseAction::Create => self.create = paused,
+            PauseAction::Execute => self.execute = paused,
+            PauseAction::Claim => self.claim = paused,
+            PauseAction::Cancel => self.cancel = paused,
+        }
+    }
+}
+
+#[cw_serde]
+pub enum PauseAction {
+    Create,
+    Execute,
+    Claim,
+    Cancel,
+}
+
+impl fmt::Display for PauseAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PauseAction::Create => write!(f, "create"),
+            PauseAction::Execute => write!(f, "execute"),
+            PauseAction::Claim => write!(f, "claim"),
+            PauseAction::Cancel => write!(f, "cancel"),
+        }
+    }
+}
+
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Fee in basis points taken from every fungible item released by the contract, in addition to the flat `Config.fee`
+#[cw_serde]
+#[derive(Default)]
+pub struct TradeFee {
+    /// Taken from the `ask` items released to the position owner
+    pub maker_bps: u16,
+    /// Taken from the `offer` items released to the executor
+    pub taker_bps: u16,
+}
+
+impl TradeFee {
+    pub fn validate(&self) -> ContractResult<()> {
+        if self.maker_bps > BPS_DENOMINATOR || self.taker_bps > BPS_DENOMINATOR {
+            return Err(ContractError::InvalidTradeFee {
+                max_bps: BPS_DENOMINATOR,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn compute(amount: Uint128, bps: u16) -> Uint128 {
+        amount.multiply_ratio(bps, BPS_DENOMINATOR)
+    }
 }
 
 #[cw_serde]
 pub struct OtcItem {
     pub item_info: OtcItemInfo,
     pub vesting_info: Option<VestingInfo>,
+    /// `true` while the item is escrowed by the contract
+    pub deposited: bool,
 }
 
 impl OtcItem {
-    pub fn validate(&self, deps: Deps) -> StdResult<()> {
+    pub fn validate(&self, deps: Deps) -> ContractResult<()> {
         if let Some(vesting) = &self.vesting_info {
             vesting.validate()?
         }
-        self.item_info.validate(deps)
+        Ok(self.item_info.validate(deps)?)
     }
 
     pub fn sendable_amount_and_update_claimed_amount(
         &mut self,
         env: &Env,
         position_status: &OtcPositionStatus,
-    ) -> StdResult<Uint128> {
+    ) -> ContractResult<Uint128> {
         match &mut self.vesting_info {
             Some(vesting_info) => {
                 let max_amount = self.item_info.get_amount();
-                let vesting_start = position_status.get_vesting_start()?;
-                let mut delta = env.block.time.seconds() - vesting_start;
+                let vesting_start = vesting_info.get_vesting_start(position_status)?;
 
-                let cliff_window = if let Some(cliff) = vesting_info.cliff {
-                    if cliff >= delta {
-                        return Ok(Uint128::zero());
-                    }
-                    cliff
-                } else {
-                    0
-                };
+                let claimabile_amount = vesting_info.vested_amount(
+                    max_amount,
+                    vesting_start,
+                    vesting_info.clock.now(&env.block),
+                ) - vesting_info.claimed;
 
-                delta -= cliff_window;
+                vesting_info.claimed += claimabile_amount;
 
-                let unchecked_claimable_amount = if let Some(vesting_window) = vesting_info.vesting
-                {
-                    max_amount * Decimal::from_ratio(min(delta, vesting_window), vesting_window)
-                } else {
-                    max_amount
-                };
+                if vesting_info.claimed == max_amount {
+                    self.deposited = false;
+                }
 
-                let claimabile_amount = unchecked_claimable_amount - vesting_info.claimed;
+                Ok(claimabile_amount)
+            }
+            // Items without vesting are sent entirely once
+            None if self.deposited => {
+                self.deposited = false;
+                Ok(self.item_info.get_amount())
+            }
+            None => Ok(Uint128::zero()),
+        }
+    }
 
-                vesting_info.claimed += claimabile_amount;
+    /// Read only version of `sendable_amount_and_update_claimed_amount` at `block`
+    pub fn claimable(
+        &self,
+        position_status: &OtcPositionStatus,
+        block: &BlockInfo,
+        fee_bps: u16,
+    ) -> ClaimableItem {
+        let amount = self.item_info.get_amount();
 
-                Ok(claimabile_amount)
+        let (vested, claimed, next_unlock) = match (&self.vesting_info, position_status) {
+            (Some(vesting_info), OtcPositionStatus::Vesting(execution)) => {
+                let vesting_start = vesting_info
+                    .start_at
+                    .unwrap_or(vesting_info.clock.start(execution));
+                let now = vesting_info.clock.now(block);
+                (
+                    vesting_info.vested_amount(amount, vesting_start, now),
+                    vesting_info.claimed,
+                    vesting_info.next_unlock(vesting_start, now),
+                )
+            }
+            (Some(vesting_info), OtcPositionStatus::Executed(_)) => {
+                (amount, vesting_info.claimed, None)
             }
-            None => Ok(self.item_info.get_amount()),
+            (None, OtcPositionStatus::Vesting(_) | OtcPositionStatus::Executed(_)) => {
+                (amount, amount, None)
+            }
+            _ => (Uint128::zero(), Uint128::zero(), None),
+        };
+
+        let claimable = vested.saturating_sub(claimed);
+
+        ClaimableItem {
+            item_info: self.item_info.clone(),
+            vested,
+            claimed,
+            claimable,
+            fee: if self.item_info.is_fungible() {
+                TradeFee::compute(claimable, fee_bps)
+            } else {
+                Uint128::zero()
+            },
+            next_unlock,
         }
     }
 }
@@ -94,46 +251,267 @@ impl From<OtcItemRegistration> for OtcItem {
         OtcItem {
             item_info: value.item_info,
             vesting_info: value.vesting.map(|val| val.into()),
+            deposited: false,
         }
     }
 }
 
 #[cw_serde]
 pub struct VestingInfo {
-    pub cliff: Option<u64>,
-    pub vesting: Option<u64>,
+    pub schedule: VestingSchedule,
+    pub clock: VestingClock,
+    /// Absolute start of the vesting, the execution time if `None`.
+    /// If the position is executed after `start_at`, the amount already vested is released with the execution
+    pub start_at: Option<u64>,
     pub claimed: Uint128,
 }
 
+/// Unit of the vesting times: seconds of `env.block.time` or blocks of `env.block.height`
+#[cw_serde]
+#[derive(Default)]
+pub enum VestingClock {
+    #[default]
+    Time,
+    Height,
+}
+
+impl VestingClock {
+    pub fn now(&self, block: &BlockInfo) -> u64 {
+        match self {
+            VestingClock::Time => block.time.seconds(),
+            VestingClock::Height => block.height,
+        }
+    }
+
+    pub fn start(&self, start: &VestingStart) -> u64 {
+        match self {
+            VestingClock::Time => start.time,
+            VestingClock::Height => start.height,
+        }
+    }
+}
+
+/// Times are measured with the `VestingClock` of the item, from the vesting start
+#[cw_serde]
+pub enum VestingSchedule {
+    /// Linear unlock over `vesting` after `cliff`
+    Linear {
+        cliff: Option<u64>,
+        vesting: Option<u64>,
+    },
+    /// `steps` equal tranches unlocked every `period` after `cliff`
+    Periodic {
+        cliff: Option<u64>,
+        period: u64,
+        steps: u32,
+    },
+    /// The cumulative `percentage` of each checkpoint is unlocked at its `time`
+    Custom { checkpoints: Vec<VestingCheckpoint> },
+}
+
+#[cw_serde]
+pub struct VestingCheckpoint {
+    pub time: u64,
+    pub percentage: Decimal,
+}
+
 impl VestingInfo {
-    pub fn validate(&self) -> StdResult<()> {
-        if self.cliff.is_none() && self.vesting.is_none() {
-            return Err(StdError::generic_err(
-                "VestingInfo must have a vesting or cliff info",
-            ));
+    pub fn validate(&self) -> ContractResult<()> {
+        match &self.schedule {
+            VestingSchedule::Linear { cliff, vesting } => {
+                if cliff.is_none() && vesting.is_none() {
+                    return Err(ContractError::MissingVestingDuration {});
+                }
+
+                if *vesting == Some(0) {
+                    return Err(ContractError::ZeroVesting {});
+                }
+
+                if *cliff == Some(0) {
+                    return Err(ContractError::ZeroCliff {});
+                }
+            }
+            VestingSchedule::Periodic {
+                cliff,
+                period,
+                steps,
+            } => {
+                if *period == 0 || *steps == 0 {
+                    return Err(ContractError::ZeroPeriod {});
+                }
+
+                if *cliff == Some(0) {
+                    return Err(ContractError::ZeroCliff {});
+                }
+            }
+            VestingSchedule::Custom { checkpoints } => {
+                let mut previous: Option<&VestingCheckpoint> = None;
+
+                for checkpoint in checkpoints {
+                    if checkpoint.percentage.is_zero() || checkpoint.percentage > Decimal::one() {
+                        return Err(ContractError::InvalidCheckpointPercentage {
+                            percentage: checkpoint.percentage,
+                        });
+                    }
+
+                    if let Some(previous) = previous {
+                        if checkpoint.time <= previous.time
+                            || checkpoint.percentage <= previous.percentage
+                        {
+                            return Err(ContractError::UnsortedCheckpoints {});
+                        }
+                    }
+
+                    previous = Some(checkpoint);
+                }
+
+                let last = previous.map(|val| val.percentage).unwrap_or_default();
+                if last != Decimal::one() {
+                    return Err(ContractError::IncompleteCheckpoints { percentage: last });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start of the vesting, available only once the position is executed
+    pub fn get_vesting_start(&self, position_status: &OtcPositionStatus) -> ContractResult<u64> {
+        let execution = position_status.get_vesting_start()?;
+        Ok(self.start_at.unwrap_or(self.clock.start(&execution)))
+    }
+
+    /// Amount of `total` vested at `now` for a vesting started at `vesting_start`.
+    /// Nothing is vested before `vesting_start`, fractions are rounded down and the whole `total` is returned
+    /// once the schedule is completed
+    pub fn vested_amount(&self, total: Uint128, vesting_start: u64, now: u64) -> Uint128 {
+        if now < vesting_start {
+            return Uint128::zero();
         }
 
-        if let Some(vesting) = self.vesting {
-            if vesting == 0 {
-                return Err(StdError::generic_err("Vesting must be > 0"));
+        let delta = now - vesting_start;
+
+        match &self.schedule {
+            VestingSchedule::Linear { cliff, vesting } => {
+                let Some(delta) = after_cliff(*cliff, delta) else {
+                    return Uint128::zero();
+                };
+
+                match vesting {
+                    Some(vesting_window) if delta < *vesting_window => {
+                        total.multiply_ratio(delta, *vesting_window)
+                    }
+                    _ => total,
+                }
+            }
+            VestingSchedule::Periodic {
+                cliff,
+                period,
+                steps,
+            } => {
+                let Some(delta) = after_cliff(*cliff, delta) else {
+                    return Uint128::zero();
+                };
+
+                let elapsed = delta / period;
+
+                if elapsed >= *steps as u64 {
+                    total
+                } else {
+                    total.multiply_ratio(elapsed, *steps as u64)
+                }
+            }
+            VestingSchedule::Custom { checkpoints } => {
+                match checkpoints.iter().rev().find(|val| val.time <= delta) {
+                    Some(checkpoint) if checkpoint.percentage == Decimal::one() => total,
+                    Some(checkpoint) => total * checkpoint.percentage,
+                    None => Uint128::zero(),
+                }
             }
         }
+    }
+
+    /// Next time after `now` the vested amount increases, `None` if fully vested
+    pub fn next_unlock(&self, vesting_start: u64, now: u64) -> Option<u64> {
+        match &self.schedule {
+            VestingSchedule::Linear { cliff, vesting } => {
+                let cliff_end = vesting_start + cliff.unwrap_or(0);
 
-        if let Some(cliff) = self.cliff {
-            if cliff == 0 {
-                return Err(StdError::generic_err("Cliff must be > 0"));
+                if now <= cliff_end {
+                    return Some(cliff_end + 1);
+                }
+
+                match vesting {
+                    Some(vesting_window) if now < cliff_end + vesting_window => Some(now + 1),
+                    _ => None,
+                }
             }
+            VestingSchedule::Periodic {
+                cliff,
+                period,
+                steps,
+            } => {
+                let cliff_end = vesting_start + cliff.unwrap_or(0);
+                let elapsed = now.saturating_sub(cliff_end) / period;
+
+                if elapsed >= *steps as u64 {
+                    None
+                } else {
+                    Some(cliff_end + (elapsed + 1) * period)
+                }
+            }
+            VestingSchedule::Custom { checkpoints } => checkpoints
+                .iter()
+                .map(|val| vesting_start + val.time)
+                .find(|val| *val > now),
         }
+    }
+}
 
-        Ok(())
+/// Time elapsed after `cliff`, `None` if the cliff is not passed
+fn after_cliff(cliff: Option<u64>, delta: u64) -> Option<u64> {
+    match cliff {
+        Some(cliff) if cliff >= delta => None,
+        Some(cliff) => Some(delta - cliff),
+        None => Some(delta),
     }
 }
 
 impl From<VestingInfoRegistration> for VestingInfo {
     fn from(value: VestingInfoRegistration) -> Self {
+        let (schedule, clock, start_at) = match value {
+            VestingInfoRegistration::Linear {
+                cliff,
+                vesting,
+                clock,
+                start_at,
+            } => (VestingSchedule::Linear { cliff, vesting }, clock, start_at),
+            VestingInfoRegistration::Periodic {
+                cliff,
+                period,
+                steps,
+                clock,
+                start_at,
+            } => (
+                VestingSchedule::Periodic {
+                    cliff,
+                    period,
+                    steps,
+                },
+                clock,
+                start_at,
+            ),
+            VestingInfoRegistration::Custom {
+                checkpoints,
+                clock,
+                start_at,
+            } => (VestingSchedule::Custom { checkpoints }, clock, start_at),
+        };
+
         VestingInfo {
-            cliff: value.cliff,
-            vesting: value.vesting,
+            schedule,
+            clock: clock.unwrap_or_default(),
+            start_at,
             claimed: Uint128::zero(),
         }
     }
@@ -164,24 +542,64 @@ impl OtcItemInfo {
         }
     }
 
+    pub fn is_fungible(&self) -> bool {
+        !matches!(self, OtcItemInfo::Cw721 { .. })
+    }
+
+    /// `true` if `other` refers to the same asset, regardless of the amount
+    pub fn is_same_asset(&self, other: &OtcItemInfo) -> bool {
+        match (self, other) {
+            (OtcItemInfo::Token { denom, .. }, OtcItemInfo::Token { denom: other, .. }) => {
+                denom == other
+            }
+            (
+                OtcItemInfo::Cw20 { contract, .. },
+                OtcItemInfo::Cw20 {
+                    contract: other, ..
+                },
+            ) => contract == other,
+            (
+                OtcItemInfo::Cw721 { contract, token_id },
+                OtcItemInfo::Cw721 {
+                    contract: other,
+                    token_id: other_id,
+                },
+            ) => contract == other && token_id == other_id,
+            _ => false,
+        }
+    }
+
+    /// Copy of a fungible item with a different `amount`
+    pub fn with_amount(&self, amount: Uint128) -> ContractResult<OtcItemInfo> {
+        match self {
+            OtcItemInfo::Token { denom, .. } => Ok(OtcItemInfo::Token {
+                denom: denom.to_owned(),
+                amount,
+            }),
+            OtcItemInfo::Cw20 { contract, .. } => Ok(OtcItemInfo::Cw20 {
+                contract: contract.to_owned(),
+                amount,
+            }),
+            OtcItemInfo::Cw721 { .. } => Err(ContractError::NonFungibleAmount {}),
+        }
+    }
+
     pub fn build_send_msg(
         &self,
         env: &Env,
         sender: &Addr,
         to: &Addr,
         override_amount: Option<Uint128>,
-    ) -> StdResult<CosmosMsg> {
+    ) -> ContractResult<CosmosMsg> {
         if let Some(override_amount) = override_amount {
             if override_amount == Uint128::zero() {
-                return Err(StdError::generic_err("Invalid 0 amount"));
+                return Err(ContractError::ZeroAmount {});
             }
         }
         match self {
             OtcItemInfo::Token { denom, amount } => {
                 if env.contract.address != sender {
-                    return Err(StdError::generic_err(
-                        "Sender for native token must be the contract itself",
-                    ));
+                    return Err(ContractError::InvalidNativeSender {});
                 }
 
                 Ok(BankMsg::Send {
@@ -224,23 +642,64 @@ impl OtcItemInfo {
 pub struct OtcPosition {
     pub id: u64,
     pub owner: Addr,
+    /// Set on creation if a single executor is allowed, otherwise by the first execution
     pub executor: Option<Addr>,
+    /// Addresses allowed to execute the position, anyone if empty
+    pub executors: Vec<Addr>,
     pub offer: Vec<OtcItem>,
     pub ask: Vec<OtcItem>,
     pub creation_time: u64,
     pub status: OtcPositionStatus,
+    /// `Config.trade_fee` at the creation of the position, applied on execution and on every claim
+    pub trade_fee: TradeFee,
+    pub expires_at: Option<Expiration>,
+    /// If set, the position can be executed in several fills by one or many executors
+    pub partial_fill: Option<PartialFill>,
+    /// Receives the `ask` items released and claimed for the owner, the owner if not set
+    pub owner_payout: Option<Addr>,
+    /// Receives the `offer` items released and claimed for the executor, the executor if not set
+    pub executor_payout: Option<Addr>,
+    /// Cw721 contract of the side tokens minted when the position entered `Vesting`.
+    /// If set, the holders of the tokens are the ones entitled to claim instead of `owner` and `executor`
+    pub position_nft: Option<Addr>,
+    /// Proposed new owner, has to send `AcceptPositionSide` to take over the owner side
+    pub pending_owner: Option<Addr>,
+    /// Proposed new executor, has to send `AcceptPositionSide` to take over the executor side
+    pub pending_executor: Option<Addr>,
+    /// `Config.fee` has been paid by `executor` with a staged deposit, it is not charged again even if the deposits
+    /// are withdrawn. Reset if the executor changes
+    pub fee_paid: bool,
 }
 
 impl OtcPosition {
-    pub fn validate(&self, deps: Deps) -> StdResult<()> {
-        if let Some(executor) = &self.executor {
-            deps.api.addr_validate(executor.as_str())?;
+    pub fn validate(&self, deps: Deps) -> ContractResult<()> {
+        for addr in self
+            .executor
+            .iter()
+            .chain(self.executors.iter())
+            .chain(self.owner_payout.iter())
+            .chain(self.executor_payout.iter())
+            .chain(self.position_nft.iter())
+            .chain(self.pending_owner.iter())
+            .chain(self.pending_executor.iter())
+        {
+            deps.api.addr_validate(addr.as_str())?;
         }
 
         for item in self.offer.iter().chain(self.ask.iter()) {
             item.validate(deps)?;
         }
 
+        if let Some(partial_fill) = &self.partial_fill {
+            partial_fill.validate()?;
+
+            for item in self.offer.iter().chain(self.ask.iter()) {
+                if !item.item_info.is_fungible() || item.vesting_info.is_some() {
+                    return Err(ContractError::PartialFillItems {});
+                }
+            }
+        }
+
         Ok(())
     }
     pub fn from_create_otc_msg(
@@ -249,41 +708,211 @@ impl OtcPosition {
         msg: CreateOtcMsg,
         id: u64,
         owner: Addr,
-    ) -> StdResult<OtcPosition> {
-        Ok(OtcPosition {
+        trade_fee: TradeFee,
+    ) -> ContractResult<OtcPosition> {
+        if let Some(expires_at) = &msg.expires_at {
+            if expires_at.is_expired(&env.block) {
+                return Err(ContractError::ExpirationPassed {
+                    expires_at: expires_at.clone(),
+                });
+            }
+        }
+
+        let mut position = OtcPosition {
             id,
             owner,
-            executor: msg
-                .executor
-                .map(|val| deps.api.addr_validate(&val))
-                .transpose()?,
+            executor: None,
+            executors: vec![],
             offer: msg.offer.into_iter().map(|val| val.into()).collect(),
             ask: msg.ask.into_iter().map(|val| val.into()).collect(),
             creation_time: env.block.time.seconds(),
-            status: OtcPositionStatus::Pending,
-        })
+            status: if msg.staged.unwrap_or(false) {
+                OtcPositionStatus::Funding
+            } else {
+                OtcPositionStatus::Pending
+            },
+            trade_fee,
+            expires_at: msg.expires_at,
+            partial_fill: msg.partial_fill.map(|val| val.into()),
+            owner_payout: None,
+            executor_payout: None,
+            position_nft: None,
+            pending_owner: None,
+            pending_executor: None,
+            fee_paid: false,
+        };
+
+        position.set_executors(deps, &msg.executors)?;
+
+        Ok(position)
     }
 
-    pub fn active(&mut self, env: &Env, executor: &Addr) -> StdResult<()> {
-        if let Some(saved_executor) = &self.executor {
-            if saved_executor != executor {
-                return Err(StdError::generic_err("Unauthorized"));
-            }
+    /// Replace the allowed executors, the executor is set directly if only one is allowed
+    pub fn set_executors(&mut self, deps: Deps, executors: &[String]) -> StdResult<()> {
+        self.executors = executors
+            .iter()
+            .map(|val| deps.api.addr_validate(val))
+            .collect::<StdResult<Vec<Addr>>>()?;
+
+        self.set_executor(if self.executors.len() == 1 {
+            self.executors.first().cloned()
         } else {
-            self.executor = Some(executor.clone())
+            None
+        });
+
+        Ok(())
+    }
+
+    /// The payout address chosen and the staged fee paid by the previous executor are reset if the executor changes
+    fn set_executor(&mut self, executor: Option<Addr>) {
+        if self.executor != executor {
+            self.executor_payout = None;
+            self.fee_paid = false;
+        }
+
+        self.executor = executor;
+    }
+
+    /// Sides of the position `sender` is a party of, ignoring the side tokens
+    pub fn sides_of(&self, sender: &Addr) -> Vec<PositionSide> {
+        let mut sides = vec![];
+
+        if *sender == self.owner {
+            sides.push(PositionSide::Owner);
+        }
+
+        if self.executor.as_ref() == Some(sender) {
+            sides.push(PositionSide::Executor);
+        }
+
+        sides
+    }
+
+    pub fn is_side_holder(&self, side: &PositionSide, sender: &Addr) -> bool {
+        match side {
+            PositionSide::Owner => self.owner == *sender,
+            PositionSide::Executor => self.executor.as_ref() == Some(sender),
+        }
+    }
+
+    /// Hand the claim rights of `side` to `new_address`, the payout address and pending transfer of the side are reset
+    pub fn transfer_side(&mut self, side: &PositionSide, new_address: Addr) {
+        match side {
+            PositionSide::Owner => {
+                self.owner = new_address;
+                self.owner_payout = None;
+                self.pending_owner = None;
+            }
+            PositionSide::Executor => {
+                self.executor = Some(new_address);
+                self.executor_payout = None;
+                self.pending_executor = None;
+            }
+        }
+    }
+
+    /// Items claimed by `side`, along with the position status and the trade fee bps applied to them
+    pub fn side_items_mut(
+        &mut self,
+        side: &PositionSide,
+    ) -> (&mut Vec<OtcItem>, &OtcPositionStatus, u16) {
+        match side {
+            PositionSide::Owner => (&mut self.ask, &self.status, self.trade_fee.maker_bps),
+            PositionSide::Executor => (&mut self.offer, &self.status, self.trade_fee.taker_bps),
+        }
+    }
+
+    /// Default receiver of the items claimed by `claimer` for `side`:
+    /// the claimer if the sides are tokenized, else the payout address of the side
+    pub fn claim_receiver(&self, side: &PositionSide, claimer: &Addr) -> Addr {
+        match (&self.position_nft, side) {
+            (Some(_), _) => claimer.clone(),
+            (None, PositionSide::Owner) => self.owner_receiver().clone(),
+            (None, PositionSide::Executor) => self.executor_receiver().unwrap().clone(),
+        }
+    }
+
+    pub fn owner_receiver(&self) -> &Addr {
+        self.owner_payout.as_ref().unwrap_or(&self.owner)
+    }
+
+    pub fn executor_receiver(&self) -> Option<&Addr> {
+        self.executor_payout.as_ref().or(self.executor.as_ref())
+    }
+
+    pub fn is_offer_deposited(&self) -> bool {
+        self.offer.iter().all(|val| val.deposited)
+    }
+
+    pub fn is_ask_deposited(&self) -> bool {
+        self.ask.iter().all(|val| val.deposited)
+    }
+
+    /// `true` if the executor already started depositing the `ask` items
+    pub fn has_ask_deposits(&self) -> bool {
+        self.ask.iter().any(|val| val.deposited)
+    }
+
+    /// Move a `Funding` position to `Pending` once every `offer` item is deposited
+    pub fn try_fund(&mut self) {
+        if self.status == OtcPositionStatus::Funding && self.is_offer_deposited() {
+            self.status = OtcPositionStatus::Pending
+        }
+    }
+
+    pub fn is_expired(&self, block: &BlockInfo) -> bool {
+        matches!(&self.expires_at, Some(val) if val.is_expired(block))
+    }
+
+    pub fn assert_executor(&self, sender: &Addr) -> ContractResult<()> {
+        let allowed = match &self.executor {
+            Some(executor) => executor == sender,
+            None => self.executors.is_empty() || self.executors.contains(sender),
         };
 
+        if !allowed {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        Ok(())
+    }
+
+    /// Executors the position is indexed by, `""` if anyone can execute it
+    pub fn index_executors(&self) -> Vec<String> {
+        match &self.executor {
+            Some(executor) => vec![executor.to_string()],
+            None if self.executors.is_empty() => vec!["".to_string()],
+            None => self.executors.iter().map(|val| val.to_string()).collect(),
+        }
+    }
+
+    pub fn active(&mut self, env: &Env, executor: &Addr) -> ContractResult<()> {
+        if self.is_expired(&env.block) {
+            return Err(ContractError::PositionExpired { id: self.id });
+        }
+
+        self.assert_executor(executor)?;
+        self.set_executor(Some(executor.clone()));
+
         match self.status {
             OtcPositionStatus::Pending => {
-                self.status = OtcPositionStatus::Vesting(env.block.time.seconds())
+                self.status = OtcPositionStatus::Vesting(VestingStart {
+                    time: env.block.time.seconds(),
+                    height: env.block.height,
+                })
+            }
+            _ => {
+                return Err(ContractError::InvalidStatus {
+                    action: "execute".to_string(),
+                    status: self.status.clone(),
+                })
             }
-            _ => return Err(StdError::generic_err("Active require status in Pending")),
         }
 
         Ok(())
     }
 
-    pub fn try_close(&mut self, env: &Env) -> StdResult<()> {
+    pub fn try_close(&mut self, env: &Env) -> ContractResult<()> {
         if let OtcPositionStatus::Vesting(..) = self.status {
             let all_items: Vec<OtcItem> = self
                 .ask
@@ -302,25 +931,155 @@ impl OtcPosition {
 
             self.status = OtcPositionStatus::Executed(env.block.time.seconds())
         } else {
-            return Err(StdError::generic_err("Try_close require status in Vesting"));
+            return Err(ContractError::InvalidStatus {
+                action: "close".to_string(),
+                status: self.status.clone(),
+            });
         }
 
         Ok(())
     }
 }
 
+/// Side of a position, the owner claims the `ask` items and the executor the `offer` items
+#[cw_serde]
+pub enum PositionSide {
+    Owner,
+    Executor,
+}
+
+impl PositionSide {
+    /// Id of the token representing the side in `OtcPosition.position_nft`
+    pub fn token_id(&self, id: u64) -> String {
+        format!("{id}-{self}")
+    }
+}
+
+impl fmt::Display for PositionSide {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PositionSide::Owner => write!(f, "owner"),
+            PositionSide::Executor => write!(f, "executor"),
+        }
+    }
+}
+
+/// Alternative `ask` proposed by a prospective executor of a `Pending` position, escrowed until accepted or refunded
+#[cw_serde]
+pub struct CounterOffer {
+    pub id: u64,
+    pub proposer: Addr,
+    pub ask: Vec<OtcItem>,
+    pub creation_time: u64,
+}
+
+impl CounterOffer {
+    pub fn from_counter_offer_msg(
+        deps: Deps,
+        env: &Env,
+        msg: CounterOfferMsg,
+        proposer: Addr,
+    ) -> ContractResult<CounterOffer> {
+        let counter_offer = CounterOffer {
+            id: msg.id,
+            proposer,
+            ask: msg.ask.into_iter().map(|val| val.into()).collect(),
+            creation_time: env.block.time.seconds(),
+        };
+
+        for item in &counter_offer.ask {
+            item.validate(deps)?;
+        }
+
+        Ok(counter_offer)
+    }
+}
+
+#[cw_serde]
+pub struct PartialFill {
+    /// Minimum fraction of the position a single fill has to execute, unless it fills all the remaining
+    pub min_fill: Decimal,
+    /// Fraction of the position already executed
+    pub filled: Decimal,
+}
+
+impl PartialFill {
+    pub fn validate(&self) -> ContractResult<()> {
+        if self.min_fill.is_zero() || self.min_fill > Decimal::one() {
+            return Err(ContractError::InvalidMinFill {
+                min_fill: self.min_fill,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check a new fill of `fill`, returning the fraction executed after it
+    pub fn assert_fill(&self, fill: Decimal) -> ContractResult<Decimal> {
+        let remaining = Decimal::one() - self.filled;
+
+        if fill.is_zero() || fill > remaining {
+            return Err(ContractError::InvalidFill { fill, remaining });
+        }
+
+        if fill < self.min_fill && fill != remaining {
+            return Err(ContractError::FillBelowMin {
+                fill,
+                min_fill: self.min_fill,
+            });
+        }
+
+        Ok(self.filled + fill)
+    }
+
+    /// Amount of an item of `total` exchanged moving from `self.filled` to `filled`.
+    /// Computed on the cumulative fraction, so the last fill always gets the exact remaining
+    pub fn fill_amount(&self, total: Uint128, filled: Decimal) -> Uint128 {
+        total * filled - total * self.filled
+    }
+
+    pub fn remaining_amount(&self, total: Uint128) -> Uint128 {
+        total - total * self.filled
+    }
+}
+
+impl From<PartialFillRegistration> for PartialFill {
+    fn from(value: PartialFillRegistration) -> Self {
+        PartialFill {
+            min_fill: value.min_fill,
+            filled: Decimal::zero(),
+        }
+    }
+}
+
+/// Time and height of the execution of a position
+#[cw_serde]
+pub struct VestingStart {
+    pub time: u64,
+    pub height: u64,
+}
+
 #[cw_serde]
 pub enum OtcPositionStatus {
+    /// The owner is still depositing the `offer` items
+    Funding,
     Pending,
-    Vesting(u64),
+    Vesting(VestingStart),
     Executed(u64),
+    /// Pending or funding position reclaimed by the owner after `expires_at`, until then it keeps its status
+    Expired(u64),
+    /// Position cancelled by the owner before execution
+    Cancelled(u64),
 }
 
 impl OtcPositionStatus {
-    pub fn get_vesting_start(&self) -> StdResult<u64> {
+    pub fn get_vesting_start(&self) -> ContractResult<VestingStart> {
         match self {
-            OtcPositionStatus::Vesting(val) => Ok(*val),
-            _ => Err(StdError::generic_err("OtcPositionStatus is not vesting")),
+            OtcPositionStatus::Vesting(val) => Ok(val.clone()),
+            _ => Err(ContractError::InvalidStatus {
+                action: "vesting".to_string(),
+                status: self.clone(),
+            }),
         }
     }
 
@@ -328,11 +1087,191 @@ impl OtcPositionStatus {
         matches!(self, OtcPositionStatus::Pending)
     }
 
+    pub fn is_in_funding(&self) -> bool {
+        matches!(self, OtcPositionStatus::Funding)
+    }
+
     pub fn as_string_ref(&self) -> String {
         match self {
+            OtcPositionStatus::Funding => "funding".to_string(),
             OtcPositionStatus::Pending => "pending".to_string(),
             OtcPositionStatus::Vesting(_) => "vesting".to_string(),
             OtcPositionStatus::Executed(_) => "executed".to_string(),
+            OtcPositionStatus::Expired(_) => "expired".to_string(),
+            OtcPositionStatus::Cancelled(_) => "cancelled".to_string(),
+        }
+    }
+}
+
+#[cw_serde]
+pub enum Expiration {
+    AtHeight(u64),
+    AtTime(u64),
+}
+
+impl Expiration {
+    pub fn is_expired(&self, block: &BlockInfo) -> bool {
+        match self {
+            Expiration::AtHeight(height) => block.height >= *height,
+            Expiration::AtTime(time) => block.time.seconds() >= *time,
+        }
+    }
+}
+
+impl fmt::Display for Expiration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expiration::AtHeight(height) => write!(f, "height:{height}"),
+            Expiration::AtTime(time) => write!(f, "time:{time}"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{Decimal, Uint128};
+
+    use crate::error::ContractError;
+
+    use super::{VestingCheckpoint, VestingClock, VestingInfo, VestingSchedule};
+
+    fn vesting_info(schedule: VestingSchedule) -> VestingInfo {
+        VestingInfo {
+            schedule,
+            clock: VestingClock::Time,
+            start_at: None,
+            claimed: Uint128::zero(),
+        }
+    }
+
+    fn custom(checkpoints: &[(u64, Decimal)]) -> VestingInfo {
+        vesting_info(VestingSchedule::Custom {
+            checkpoints: checkpoints
+                .iter()
+                .map(|(time, percentage)| VestingCheckpoint {
+                    time: *time,
+                    percentage: *percentage,
+                })
+                .collect(),
+        })
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_periodic() {
+        let total = Uint128::new(1_000);
+        let info = vesting_info(VestingSchedule::Periodic { cliff: None, period: 10, steps: 4 });
+
+        assert_eq!(info.vested_amount(total, 100, 100), Uint128::zero());
+        assert_eq!(info.next_unlock(100, 100), Some(110));
+        assert_eq!(info.vested_amount(total, 100, 109), Uint128::zero());
+        assert_eq!(info.vested_amount(total, 100, 110), Uint128::new(250));
+        assert_eq!(info.next_unlock(100, 110), Some(120));
+        assert_eq!(info.vested_amount(total, 100, 139), Uint128::new(750));
+        assert_eq!(info.next_unlock(100, 139), Some(140));
+        assert_eq!(info.vested_amount(total, 100, 140), total);
+        assert_eq!(info.next_unlock(100, 140), None);
+        assert_eq!(info.vested_amount(total, 100, 1_000), total);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_periodic_with_cliff() {
+        let total = Uint128::new(1_000);
+        let info = vesting_info(VestingSchedule::Periodic { cliff: Some(50), period: 10, steps: 4 });
+
+        assert_eq!(info.vested_amount(total, 100, 150), Uint128::zero());
+        assert_eq!(info.next_unlock(100, 120), Some(160));
+        assert_eq!(info.next_unlock(100, 150), Some(160));
+        assert_eq!(info.vested_amount(total, 100, 159), Uint128::zero());
+        assert_eq!(info.vested_amount(total, 100, 160), Uint128::new(250));
+        assert_eq!(info.vested_amount(total, 100, 189), Uint128::new(750));
+        assert_eq!(info.next_unlock(100, 189), Some(190));
+        assert_eq!(info.vested_amount(total, 100, 190), total);
+        assert_eq!(info.next_unlock(100, 190), None);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_periodic_last_tranche() {
+        let total = Uint128::new(1_000);
+        let info = vesting_info(VestingSchedule::Periodic { cliff: None, period: 10, steps: 3 });
+
+        assert_eq!(info.vested_amount(total, 0, 10), Uint128::new(333));
+        assert_eq!(info.vested_amount(total, 0, 20), Uint128::new(666));
+        assert_eq!(info.vested_amount(total, 0, 30), total);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_custom() {
+        let total = Uint128::new(999);
+        let info = custom(&[(0, Decimal::percent(10)), (100, Decimal::percent(50)), (200, Decimal::one())]);
+        info.validate().unwrap();
+
+        // The checkpoint at 0 is unlocked with the start of the vesting, not before
+        assert_eq!(info.vested_amount(total, 100, 99), Uint128::zero());
+        assert_eq!(info.next_unlock(100, 99), Some(100));
+        assert_eq!(info.vested_amount(total, 100, 100), Uint128::new(99));
+        assert_eq!(info.next_unlock(100, 100), Some(200));
+        assert_eq!(info.vested_amount(total, 100, 199), Uint128::new(99));
+        assert_eq!(info.vested_amount(total, 100, 200), Uint128::new(499));
+        assert_eq!(info.next_unlock(100, 200), Some(300));
+        assert_eq!(info.vested_amount(total, 100, 299), Uint128::new(499));
+
+        // The last checkpoint returns exactly total
+        assert_eq!(info.vested_amount(total, 100, 300), total);
+        assert_eq!(info.next_unlock(100, 300), None);
+
+        let total = Uint128::new(1_000);
+        let info = custom(&[(10, Decimal::from_ratio(1_u128, 3_u128)), (20, Decimal::from_ratio(2_u128, 3_u128)), (30, Decimal::one())]);
+        assert_eq!(info.vested_amount(total, 0, 0), Uint128::zero());
+        assert_eq!(info.next_unlock(0, 0), Some(10));
+        assert_eq!(info.vested_amount(total, 0, 10), Uint128::new(333));
+        assert_eq!(info.vested_amount(total, 0, 20), Uint128::new(666));
+        assert_eq!(info.vested_amount(total, 0, 30), total);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_validate() {
+        // Custom
+
+        let err = custom(&[(100, Decimal::percent(50)), (50, Decimal::one())]).validate().unwrap_err();
+        assert_eq!(err, ContractError::UnsortedCheckpoints {});
+
+        let err = custom(&[(100, Decimal::percent(50)), (100, Decimal::one())]).validate().unwrap_err();
+        assert_eq!(err, ContractError::UnsortedCheckpoints {});
+
+        let err = custom(&[(10, Decimal::percent(60)), (20, Decimal::percent(50)), (30, Decimal::one())]).validate().unwrap_err();
+        assert_eq!(err, ContractError::UnsortedCheckpoints {});
+
+        let err = custom(&[(10, Decimal::percent(50))]).validate().unwrap_err();
+        assert_eq!(err, ContractError::IncompleteCheckpoints { percentage: Decimal::percent(50) });
+
+        let err = custom(&[]).validate().unwrap_err();
+        assert_eq!(err, ContractError::IncompleteCheckpoints { percentage: Decimal::zero() });
+
+        let err = custom(&[(10, Decimal::zero()), (20, Decimal::one())]).validate().unwrap_err();
+        assert_eq!(err, ContractError::InvalidCheckpointPercentage { percentage: Decimal::zero() });
+
+        // Periodic
+
+        let err = vesting_info(VestingSchedule::Periodic { cliff: None, period: 0, steps: 4 }).validate().unwrap_err();
+        assert_eq!(err, ContractError::ZeroPeriod {});
+
+        let err = vesting_info(VestingSchedule::Periodic { cliff: None, period: 10, steps: 0 }).validate().unwrap_err();
+        assert_eq!(err, ContractError::ZeroPeriod {});
+
+        let err = vesting_info(VestingSchedule::Periodic { cliff: Some(0), period: 10, steps: 4 }).validate().unwrap_err();
+        assert_eq!(err, ContractError::ZeroCliff {});
+
+        // Linear
+
+        let err = vesting_info(VestingSchedule::Linear { cliff: None, vesting: None }).validate().unwrap_err();
+        assert_eq!(err, ContractError::MissingVestingDuration {});
+
+        let err = vesting_info(VestingSchedule::Linear { cliff: None, vesting: Some(0) }).validate().unwrap_err();
+        assert_eq!(err, ContractError::ZeroVesting {});
+    }
+}
@@ -0,0 +1,157 @@
+use cosmwasm_std::{Decimal, StdError, Uint128};
+use thiserror::Error;
+
+use crate::definitions::{Expiration, OtcItemInfo, OtcPositionStatus, PauseAction};
+
+pub type ContractResult<T> = Result<T, ContractError>;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("{action} is paused")]
+    Paused { action: PauseAction },
+
+    // Position
+    #[error("{action} not allowed on a position in status {}", .status.as_string_ref())]
+    InvalidStatus {
+        action: String,
+        status: OtcPositionStatus,
+    },
+
+    #[error("Position {id} expired")]
+    PositionExpired { id: u64 },
+
+    #[error("Expiration {expires_at} already passed")]
+    ExpirationPassed { expires_at: Expiration },
+
+    #[error("{action} not allowed on partial fill positions")]
+    PartialFillNotAllowed { action: String },
+
+    #[error("Fill allowed only on partial fill positions")]
+    FillNotAllowed {},
+
+    #[error("{action} not allowed with ask items deposited")]
+    AskDeposited { action: String },
+
+    #[error("Counter offer already submitted by {proposer}")]
+    CounterOfferExists { proposer: String },
+
+    #[error("Position {id} sides are tokenized")]
+    PositionTokenized { id: u64 },
+
+    #[error("Nothing to claim")]
+    NothingToClaim {},
+
+    #[error("Too many positions, max: {max}")]
+    TooManyPositions { max: u32 },
+
+    // Items and funds
+    #[error("Coin not received {denom}")]
+    CoinNotReceived { denom: String },
+
+    #[error("Amount received for {denom} is too low: expected: {expected}, received: {received}")]
+    InsufficientFunds {
+        denom: String,
+        expected: Uint128,
+        received: Uint128,
+    },
+
+    #[error("Unexpected denom received {denom}")]
+    UnexpectedDenom { denom: String },
+
+    #[error("Unexpected cw20 received {contract}")]
+    UnexpectedCw20 { contract: String },
+
+    #[error("Unexpected nft received {contract} {token_id}")]
+    UnexpectedNft { contract: String, token_id: String },
+
+    #[error("Fee nft {token_id} is not owned by {payer}")]
+    FeeNftNotOwned { token_id: String, payer: String },
+
+    #[error("Nft {token_id} is not owned by {owner}")]
+    NftNotOwned { token_id: String, owner: String },
+
+    #[error("Invalid item index {index}")]
+    InvalidItemIndex { index: u64 },
+
+    #[error("Item {index} already deposited")]
+    ItemAlreadyDeposited { index: u64 },
+
+    #[error("Duplicated item {item:?}")]
+    DuplicatedItem { item: OtcItemInfo },
+
+    #[error("Invalid 0 amount")]
+    ZeroAmount {},
+
+    #[error("Cw721 amount can't be changed")]
+    NonFungibleAmount {},
+
+    #[error("Sender for native token must be the contract itself")]
+    InvalidNativeSender {},
+
+    #[error("Trade fee must be <= {max_bps} bps")]
+    InvalidTradeFee { max_bps: u16 },
+
+    // Vesting
+    #[error("VestingInfo must have a vesting or cliff info")]
+    MissingVestingDuration {},
+
+    #[error("Vesting must be > 0")]
+    ZeroVesting {},
+
+    #[error("Cliff must be > 0")]
+    ZeroCliff {},
+
+    #[error("Period and steps must be > 0")]
+    ZeroPeriod {},
+
+    #[error("Checkpoint percentage must be > 0 and <= 1, received: {percentage}")]
+    InvalidCheckpointPercentage { percentage: Decimal },
+
+    #[error("Checkpoints must be sorted by increasing time and percentage")]
+    UnsortedCheckpoints {},
+
+    #[error("Last checkpoint percentage must be 1, received: {percentage}")]
+    IncompleteCheckpoints { percentage: Decimal },
+
+    // Partial fill
+    #[error("Min fill must be > 0 and <= 1, received: {min_fill}")]
+    InvalidMinFill { min_fill: Decimal },
+
+    #[error("Invalid fill {fill}, remaining: {remaining}")]
+    InvalidFill { fill: Decimal, remaining: Decimal },
+
+    #[error("Fill {fill} is lower than min fill {min_fill}")]
+    FillBelowMin { fill: Decimal, min_fill: Decimal },
+
+    #[error("Fill {fill} is too small, an item would be exchanged for 0")]
+    FillTooSmall { fill: Decimal },
+
+    #[error("Partial fill requires only fungible items without vesting")]
+    PartialFillItems {},
+
+    // Migration
+    #[error("Cannot migrate from contract {received}, expected {expected}")]
+    InvalidContractName { expected: String, received: String },
+
+    #[error("Cannot migrate from version {from} to the older version {to}")]
+    CannotDowngrade { from: String, to: String },
+
+    #[error("Invalid contract version {version}")]
+    InvalidVersion { version: String },
+}
+
+/// Queries share the validation code of the executions, but return a `StdError`
+impl From<ContractError> for StdError {
+    fn from(err: ContractError) -> Self {
+        match err {
+            ContractError::Std(err) => err,
+            err => StdError::generic_err(err.to_string()),
+        }
+    }
+}
@@ -1,2 +1,3 @@
 pub mod definitions;
+pub mod error;
 pub mod msgs;
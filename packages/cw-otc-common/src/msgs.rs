@@ -1,38 +1,164 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Order;
+use cosmwasm_std::{Decimal, Order, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw721::Cw721ReceiveMsg;
 
-use super::definitions::{OtcItemInfo, OtcPosition};
+use super::definitions::{
+    Config, CounterOffer, Expiration, OtcItemInfo, OtcPosition, PositionSide, TradeFee,
+    VestingCheckpoint, VestingClock,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub owner: String,
     pub fee: Vec<OtcItemInfo>,
     pub fee_collector: String,
+    pub strict_funds: Option<bool>,
+    pub trade_fee: Option<TradeFee>,
+    pub position_nft: Option<String>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
     CreateOtc(CreateOtcMsg),
     ExecuteOtc(ExecuteOtcMsg),
+    DepositOtc(DepositOtcMsg),
+    UpdateOtc(UpdateOtcMsg),
+    CounterOffer(CounterOfferMsg),
+    AcceptCounterOffer(CounterOfferActionMsg),
+    RejectCounterOffer(CounterOfferActionMsg),
     ClaimOtc(ClaimOtcMsg),
+    ClaimMany(ClaimManyMsg),
+    SetPayoutAddress(SetPayoutAddressMsg),
+    TransferPositionSide(TransferPositionSideMsg),
+    AcceptPositionSide(AcceptPositionSideMsg),
     CancelOtc(CancelOtcMsg),
+    UpdateConfig(UpdateConfigMsg),
+    SetPause(SetPauseMsg),
+    SetGuardian(SetGuardianMsg),
+    ProposeNewOwner(ProposeNewOwnerMsg),
+    AcceptOwnership {},
+    Receive(Cw20ReceiveMsg),
+    ReceiveNft(Cw721ReceiveMsg),
+}
+
+/// Msg embedded in `Cw20ReceiveMsg`, the tokens sent are matched against the `OtcItemInfo::Cw20` items and fee
+#[cw_serde]
+pub enum Cw20HookMsg {
+    CreateOtc(CreateOtcMsg),
+    ExecuteOtc(ExecuteOtcMsg),
+    DepositOtc(DepositOtcMsg),
+    UpdateOtc(UpdateOtcMsg),
+    CounterOffer(CounterOfferMsg),
+}
+
+/// Msg embedded in `Cw721ReceiveMsg`, the nft sent is matched against the `OtcItemInfo::Cw721` items and fee
+#[cw_serde]
+pub enum Cw721HookMsg {
+    CreateOtc(CreateOtcMsg),
+    ExecuteOtc(ExecuteOtcMsg),
+    DepositOtc(DepositOtcMsg),
+    UpdateOtc(UpdateOtcMsg),
+    CounterOffer(CounterOfferMsg),
 }
 
 #[cw_serde]
 pub struct CreateOtcMsg {
-    pub executor: Option<String>,
+    /// Addresses allowed to execute the position, anyone if empty
+    pub executors: Vec<String>,
     pub offer: Vec<OtcItemRegistration>,
     pub ask: Vec<OtcItemRegistration>,
+    pub expires_at: Option<Expiration>,
+    /// If `true` the position is created in `Funding` status, escrowing only the `offer` items sent with the msg.
+    /// The remaining items are deposited with `DepositOtc`
+    pub staged: Option<bool>,
+    /// Allow the position to be executed in several fills, only for `Token` and `Cw20` items without vesting
+    pub partial_fill: Option<PartialFillRegistration>,
 }
 
 #[cw_serde]
 pub struct ExecuteOtcMsg {
     pub id: u64,
+    /// Fraction of the position to execute, only for partial fill positions. Default to all the remaining
+    pub fill: Option<Decimal>,
 }
 
+/// Deposit part of the items of a position: `offer` items by the owner while `Funding`,
+/// `ask` items by the designated executor while `Pending`. The position is executed once every `ask` item is deposited
+#[cw_serde]
+pub struct DepositOtcMsg {
+    pub id: u64,
+    /// Indexes of the items to deposit, the `Cw20` and `Cw721` not sent with the msg are pulled through allowance.
+    /// If `None` every item covered by the assets sent with the msg is deposited
+    pub items: Option<Vec<u32>>,
+}
+
+/// Update a `Pending` position, only by the owner. The fields set to `None` are not changed
+#[cw_serde]
+pub struct UpdateOtcMsg {
+    pub id: u64,
+    /// Addresses allowed to execute the position, anyone if empty
+    pub executors: Option<Vec<String>>,
+    /// The difference with the current `offer` items is deposited or refunded to the owner
+    pub offer: Option<Vec<OtcItemRegistration>>,
+    pub ask: Option<Vec<OtcItemRegistration>>,
+    pub expires_at: Option<Expiration>,
+}
+
+/// Propose a different `ask` for a `Pending` position, the items and the fee are escrowed with the msg
+#[cw_serde]
+pub struct CounterOfferMsg {
+    pub id: u64,
+    pub ask: Vec<OtcItemRegistration>,
+}
+
+/// Accept a counter offer, only by the position owner. With `RejectCounterOffer` the counter offer is refunded,
+/// by the owner or by the proposer to withdraw it
+#[cw_serde]
+pub struct CounterOfferActionMsg {
+    pub id: u64,
+    pub proposer: String,
+}
+
+/// Claim the vested items of the sides of the position the sender is entitled to, both if it holds both side tokens
 #[cw_serde]
 pub struct ClaimOtcMsg {
     pub id: u64,
+    /// Receives the claimed items instead of the payout address of the sender side
+    pub recipient: Option<String>,
+}
+
+/// Hand the claim rights of a side of a position to `new_address`.
+/// If `require_acceptance`, `new_address` becomes the pending holder of the side until it sends `AcceptPositionSide`,
+/// a new transfer replaces the pending one
+#[cw_serde]
+pub struct TransferPositionSideMsg {
+    pub id: u64,
+    pub side: PositionSide,
+    pub new_address: String,
+    pub require_acceptance: Option<bool>,
+}
+
+#[cw_serde]
+pub struct AcceptPositionSideMsg {
+    pub id: u64,
+    pub side: PositionSide,
+}
+
+/// Set the payout address of the sender side of the position, the owner or the executor. `None` to unset it
+#[cw_serde]
+pub struct SetPayoutAddressMsg {
+    pub id: u64,
+    pub address: Option<String>,
+}
+
+#[cw_serde]
+pub struct ClaimManyMsg {
+    /// Positions to claim, if not provided the `vesting` positions of the sender as owner and executor.
+    /// At most `MAX_LIMIT` ids
+    pub ids: Option<Vec<u64>>,
+    /// Max positions loaded from the indexes when `ids` is not provided
+    pub limit: Option<u32>,
 }
 
 #[cw_serde]
@@ -40,9 +166,43 @@ pub struct CancelOtcMsg {
     pub id: u64,
 }
 
+#[cw_serde]
+pub struct UpdateConfigMsg {
+    pub fee: Option<Vec<OtcItemInfo>>,
+    pub fee_collector: Option<String>,
+    pub strict_funds: Option<bool>,
+    pub trade_fee: Option<TradeFee>,
+    pub position_nft: Option<String>,
+}
+
+/// Pause (`true`) or unpause (`false`) each action, `None` keeps the current state.
+/// The owner can pause and unpause, the guardian can only pause
+#[cw_serde]
+pub struct SetPauseMsg {
+    pub create: Option<bool>,
+    pub execute: Option<bool>,
+    pub claim: Option<bool>,
+    pub cancel: Option<bool>,
+}
+
+/// Set the address allowed to pause, `None` removes the guardian
+#[cw_serde]
+pub struct SetGuardianMsg {
+    pub guardian: Option<String>,
+}
+
+/// Start a two-step ownership transfer, the proposed owner has to send `AcceptOwnership` to complete it.
+/// `None` removes a pending proposal.
+#[cw_serde]
+pub struct ProposeNewOwnerMsg {
+    pub owner: Option<String>,
+}
+
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
+    #[returns(Config)]
+    Config {},
     #[returns(OtcPosition)]
     Position { id: u64 },
     #[returns(Vec<OtcPosition>)]
@@ -52,15 +212,66 @@ pub enum QueryMsg {
         filters: Option<QueryPositionsFilter>,
         order: Option<QueryPositionsFilterOrder>,
     },
+    /// Amounts `ClaimOtc` would send to `address` at `at_time` and `at_height`, default to the current block.
+    /// The `ask` items then the `offer` items if `address` holds both sides
+    #[returns(Vec<ClaimableItem>)]
+    Claimable {
+        id: u64,
+        address: String,
+        at_time: Option<u64>,
+        at_height: Option<u64>,
+    },
+    #[returns(Vec<CounterOffer>)]
+    CounterOffers {
+        id: u64,
+        limit: Option<u32>,
+        start_after: Option<String>,
+    },
 }
 
 #[cw_serde]
 pub struct MigrateMsg {}
 
 #[cw_serde]
-pub struct VestingInfoRegistration {
-    pub cliff: Option<u64>,
-    pub vesting: Option<u64>,
+pub struct ClaimableItem {
+    pub item_info: OtcItemInfo,
+    pub vested: Uint128,
+    pub claimed: Uint128,
+    pub claimable: Uint128,
+    /// Part of `claimable` taken as trade fee
+    pub fee: Uint128,
+    /// Next time, or height for `VestingClock::Height`, the vested amount increases.
+    /// `None` if fully vested or the vesting is not started
+    pub next_unlock: Option<u64>,
+}
+
+/// See `VestingSchedule`, times are measured with `clock`, default to `VestingClock::Time`.
+/// `start_at` anchors the vesting start to an absolute time or height instead of the execution
+#[cw_serde]
+pub enum VestingInfoRegistration {
+    Linear {
+        cliff: Option<u64>,
+        vesting: Option<u64>,
+        clock: Option<VestingClock>,
+        start_at: Option<u64>,
+    },
+    Periodic {
+        cliff: Option<u64>,
+        period: u64,
+        steps: u32,
+        clock: Option<VestingClock>,
+        start_at: Option<u64>,
+    },
+    Custom {
+        checkpoints: Vec<VestingCheckpoint>,
+        clock: Option<VestingClock>,
+        start_at: Option<u64>,
+    },
+}
+
+#[cw_serde]
+pub struct PartialFillRegistration {
+    pub min_fill: Decimal,
 }
 
 #[cw_serde]
@@ -78,17 +289,25 @@ pub struct QueryPositionsFilter {
 
 #[cw_serde]
 pub enum QueryPositionsFilterStatus {
+    Funding,
     Vesting,
     Pending,
     Executed,
+    /// Positions already reclaimed by the owner with `CancelOtc`. A position past its `expires_at`
+    /// keeps its `Pending` or `Funding` status, and is listed under it, until reclaimed
+    Expired,
+    Cancelled,
 }
 
 impl QueryPositionsFilterStatus {
     pub fn as_string(&self) -> String {
         match self {
+            QueryPositionsFilterStatus::Funding => "funding".to_string(),
             QueryPositionsFilterStatus::Vesting => "vesting".to_string(),
             QueryPositionsFilterStatus::Pending => "pending".to_string(),
             QueryPositionsFilterStatus::Executed => "executed".to_string(),
+            QueryPositionsFilterStatus::Expired => "expired".to_string(),
+            QueryPositionsFilterStatus::Cancelled => "cancelled".to_string(),
         }
     }
 }